@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use winit::event::{
+    ElementState, KeyboardInput, ModifiersState, MouseButton, VirtualKeyCode, WindowEvent,
+};
+
+use crate::input::{InputButton, InputState};
+
+// the action run() listens to for closing the app, bound to Escape unless you rebind it
+pub const QUIT_ACTION: &str = "quit";
+
+// the axes CameraController reads, bound to the arrow keys unless you rebind them
+pub const CAMERA_FORWARD_AXIS: &str = "camera_forward";
+pub const CAMERA_RIGHT_AXIS: &str = "camera_right";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Binding {
+    pub button: InputButton,
+    // bindings without modifiers fire regardless of what modifiers are held, bindings with
+    // modifiers need exactly those held
+    pub modifiers: ModifiersState,
+}
+
+impl Binding {
+    pub fn key(key: VirtualKeyCode) -> Self {
+        Self {
+            button: InputButton::Key(key),
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    pub fn mouse(button: MouseButton) -> Self {
+        Self {
+            button: InputButton::Mouse(button),
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    pub fn with_modifiers(mut self, modifiers: ModifiersState) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    fn modifiers_match(&self, modifiers: ModifiersState) -> bool {
+        self.modifiers.is_empty() || self.modifiers == modifiers
+    }
+
+    fn is_held(&self, input: &InputState) -> bool {
+        input.is_pressed(self.button) && self.modifiers_match(input.modifiers())
+    }
+}
+
+impl From<VirtualKeyCode> for Binding {
+    fn from(key: VirtualKeyCode) -> Self {
+        Binding::key(key)
+    }
+}
+
+impl From<MouseButton> for Binding {
+    fn from(button: MouseButton) -> Self {
+        Binding::mouse(button)
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.ctrl() {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.shift() {
+            write!(f, "Shift+")?;
+        }
+        if self.modifiers.alt() {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.logo() {
+            write!(f, "Logo+")?;
+        }
+        match self.button {
+            InputButton::Key(key) => write!(f, "{:?}", key),
+            InputButton::Mouse(MouseButton::Other(n)) => write!(f, "Mouse:{}", n),
+            InputButton::Mouse(button) => write!(f, "Mouse:{:?}", button),
+        }
+    }
+}
+
+impl std::str::FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let button = parts.pop().filter(|b| !b.is_empty()).ok_or("empty binding")?;
+
+        let mut modifiers = ModifiersState::empty();
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" => ModifiersState::CTRL,
+                "shift" => ModifiersState::SHIFT,
+                "alt" => ModifiersState::ALT,
+                "logo" => ModifiersState::LOGO,
+                _ => return Err(format!("unknown modifier {}", modifier)),
+            };
+        }
+
+        let button = match button.strip_prefix("Mouse:") {
+            Some("Left") => InputButton::Mouse(MouseButton::Left),
+            Some("Right") => InputButton::Mouse(MouseButton::Right),
+            Some("Middle") => InputButton::Mouse(MouseButton::Middle),
+            Some(other) => InputButton::Mouse(MouseButton::Other(
+                other.parse().map_err(|_| format!("unknown mouse button {}", other))?
+            )),
+            None => InputButton::Key(key_from_name(button).ok_or_else(|| format!("unknown key {}", button))?),
+        };
+
+        Ok(Binding { button, modifiers })
+    }
+}
+
+// a -1..1 value from a pair of bindings, e.g. A/D for strafing
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AxisBinding {
+    pub negative: Binding,
+    pub positive: Binding,
+}
+
+#[derive(Debug)]
+pub enum ActionMapError {
+    Io(std::io::Error),
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ActionMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionMapError::Io(e) => write!(f, "failed to read action map: {}", e),
+            ActionMapError::Parse { line, message } => write!(f, "action map line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ActionMapError {}
+
+impl From<std::io::Error> for ActionMapError {
+    fn from(e: std::io::Error) -> Self {
+        ActionMapError::Io(e)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ActionMap {
+    actions: HashMap<String, Vec<Binding>>,
+    axes: HashMap<String, Vec<AxisBinding>>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // quit on Escape and nothing else
+    pub fn with_default_quit() -> Self {
+        let mut actions = Self::new();
+        actions.bind(QUIT_ACTION, Binding::key(VirtualKeyCode::Escape));
+        actions
+    }
+
+    // what the skeleton starts with, quit on Escape and the camera axes on the arrow keys
+    pub fn with_defaults() -> Self {
+        let mut actions = Self::with_default_quit();
+        actions.bind_axis(CAMERA_FORWARD_AXIS, VirtualKeyCode::Down, VirtualKeyCode::Up);
+        actions.bind_axis(CAMERA_RIGHT_AXIS, VirtualKeyCode::Left, VirtualKeyCode::Right);
+        actions
+    }
+
+    pub fn bind<B: Into<Binding>>(&mut self, action: &str, binding: B) {
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        let binding = binding.into();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn bind_axis<N: Into<Binding>, P: Into<Binding>>(&mut self, axis: &str, negative: N, positive: P) {
+        self.axes.entry(axis.to_string()).or_default().push(AxisBinding {
+            negative: negative.into(),
+            positive: positive.into(),
+        });
+    }
+
+    // removes every binding of the action (or axis)
+    pub fn unbind(&mut self, name: &str) {
+        self.actions.remove(name);
+        self.axes.remove(name);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn is_active(&self, action: &str, input: &InputState) -> bool {
+        self.bindings(action).iter().any(|b| b.is_held(input))
+    }
+
+    pub fn just_activated(&self, action: &str, input: &InputState) -> bool {
+        self.bindings(action).iter()
+            .any(|b| input.just_pressed(b.button) && b.modifiers_match(input.modifiers()))
+    }
+
+    pub fn just_deactivated(&self, action: &str, input: &InputState) -> bool {
+        self.bindings(action).iter().any(|b| input.just_released(b.button))
+    }
+
+    pub fn axis(&self, axis: &str, input: &InputState) -> f32 {
+        let value: f32 = self.axis_bindings(axis).iter()
+            .map(|a| a.positive.is_held(input) as i32 as f32 - a.negative.is_held(input) as i32 as f32)
+            .sum();
+        value.clamp(-1.0, 1.0)
+    }
+
+    // whether this single event is a press of one of the action's bindings. key repeats don't
+    // count, so held keys only fire once. input has to have seen the event already (run() does
+    // that before anything else)
+    pub fn matches_event(&self, action: &str, event: &WindowEvent, input: &InputState) -> bool {
+        let button = match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(keycode),
+                    ..
+                },
+                ..
+            } => InputButton::Key(*keycode),
+            WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } => InputButton::Mouse(*button),
+            _ => return false,
+        };
+        if input.is_repeat() {
+            return false;
+        }
+        self.bindings(action).iter().any(|b| b.button == button && b.modifiers_match(input.modifiers()))
+    }
+
+    // one action per line, `name = binding, binding` for actions and
+    // `axis name = negative/positive, negative/positive` for axes. # starts a comment
+    pub fn parse(config: &str) -> Result<Self, ActionMapError> {
+        let mut actions = Self::new();
+        for (index, line) in config.lines().enumerate() {
+            let parse_error = |message: String| ActionMapError::Parse { line: index + 1, message };
+
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, bindings) = line.split_once('=')
+                .ok_or_else(|| parse_error("expected `name = bindings`".to_string()))?;
+            let name = name.trim();
+            let bindings = bindings.split(',').map(str::trim).filter(|b| !b.is_empty());
+
+            match name.strip_prefix("axis ") {
+                Some(axis) => {
+                    let axis = axis.trim();
+                    // an axis with no bindings is still written out so unbinding survives a save
+                    actions.axes.entry(axis.to_string()).or_default();
+                    for pair in bindings {
+                        let (negative, positive) = pair.split_once('/')
+                            .ok_or_else(|| parse_error(format!("expected `negative/positive`, got {}", pair)))?;
+                        let negative: Binding = negative.trim().parse().map_err(parse_error)?;
+                        let positive: Binding = positive.trim().parse().map_err(parse_error)?;
+                        actions.bind_axis(axis, negative, positive);
+                    }
+                },
+                None => {
+                    actions.actions.entry(name.to_string()).or_default();
+                    for binding in bindings {
+                        let binding: Binding = binding.parse().map_err(parse_error)?;
+                        actions.bind(name, binding);
+                    }
+                },
+            }
+        }
+        Ok(actions)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ActionMapError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for ActionMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // sorted so saved files dont reshuffle every time
+        let mut actions: Vec<_> = self.actions.iter().collect();
+        actions.sort_by_key(|(name, _)| name.as_str());
+        for (name, bindings) in actions {
+            let bindings: Vec<String> = bindings.iter().map(Binding::to_string).collect();
+            writeln!(f, "{}", format!("{} = {}", name, bindings.join(", ")).trim_end())?;
+        }
+
+        let mut axes: Vec<_> = self.axes.iter().collect();
+        axes.sort_by_key(|(name, _)| name.as_str());
+        for (name, bindings) in axes {
+            let bindings: Vec<String> = bindings.iter()
+                .map(|a| format!("{}/{}", a.negative, a.positive))
+                .collect();
+            writeln!(f, "{}", format!("axis {} = {}", name, bindings.join(", ")).trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        pub(crate) fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+            match name {
+                $(stringify!($key) => Some(VirtualKeyCode::$key),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+// every VirtualKeyCode, spelled the same as its Debug output
+key_names!(
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Escape,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24,
+    Snapshot, Scroll, Pause, Insert, Home, Delete, End, PageDown, PageUp,
+    Left, Up, Right, Down,
+    Back, Return, Space, Compose, Caret, Numlock,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    NumpadAdd, NumpadDivide, NumpadDecimal, NumpadComma, NumpadEnter, NumpadEquals,
+    NumpadMultiply, NumpadSubtract,
+    AbntC1, AbntC2, Apostrophe, Apps, Asterisk, At, Ax, Backslash, Calculator, Capital, Colon,
+    Comma, Convert, Equals, Grave, Kana, Kanji, LAlt, LBracket, LControl, LShift, LWin, Mail,
+    MediaSelect, MediaStop, Minus, Mute, MyComputer, NavigateForward, NavigateBackward,
+    NextTrack, NoConvert, OEM102, Period, PlayPause, Plus, Power, PrevTrack, RAlt, RBracket,
+    RControl, RShift, RWin, Semicolon, Slash, Sleep, Stop, Sysrq, Tab, Underline, Unlabeled,
+    VolumeDown, VolumeUp, Wake, WebBack, WebFavorites, WebForward, WebHome, WebRefresh,
+    WebSearch, WebStop, Yen, Copy, Paste, Cut,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::RecordedInput;
+
+    fn key_event(key: VirtualKeyCode, state: ElementState) -> WindowEvent<'static> {
+        RecordedInput::Keyboard { scancode: 0, keycode: Some(key), state }.to_window_event().unwrap()
+    }
+
+    #[test]
+    fn key_repeat_only_fires_once() {
+        let actions = ActionMap::with_defaults();
+        let mut input = InputState::new();
+        let press = key_event(VirtualKeyCode::Escape, ElementState::Pressed);
+
+        input.process_window_event(&press);
+        assert!(actions.matches_event(QUIT_ACTION, &press, &input));
+        input.process_window_event(&press);
+        assert!(!actions.matches_event(QUIT_ACTION, &press, &input));
+
+        input.process_window_event(&key_event(VirtualKeyCode::Escape, ElementState::Released));
+        input.process_window_event(&press);
+        assert!(actions.matches_event(QUIT_ACTION, &press, &input));
+    }
+
+    #[test]
+    fn default_camera_axes() {
+        let actions = ActionMap::with_defaults();
+        let mut input = InputState::new();
+        input.process_window_event(&key_event(VirtualKeyCode::Up, ElementState::Pressed));
+        input.process_window_event(&key_event(VirtualKeyCode::Left, ElementState::Pressed));
+        assert_eq!(actions.axis(CAMERA_FORWARD_AXIS, &input), 1.0);
+        assert_eq!(actions.axis(CAMERA_RIGHT_AXIS, &input), -1.0);
+    }
+
+    #[test]
+    fn config_round_trip() {
+        let config = "jump = Space, Mouse:Left\nquit = Ctrl+Q\naxis strafe = A/D\n";
+        let actions = ActionMap::parse(config).unwrap();
+        assert_eq!(actions.bindings("quit"), &[Binding::key(VirtualKeyCode::Q).with_modifiers(ModifiersState::CTRL)]);
+        assert_eq!(actions.to_string(), config);
+        assert!(matches!(ActionMap::parse("jump = Nope"), Err(ActionMapError::Parse { line: 1, .. })));
+    }
+}
@@ -0,0 +1,141 @@
+// ====== adapter selection ======
+
+#[derive(Clone, Debug)]
+pub struct AdapterDescription {
+    pub info: wgpu::AdapterInfo,
+    pub features: wgpu::Features,
+    pub limits: wgpu::Limits,
+}
+
+impl AdapterDescription {
+    pub fn of(adapter: &wgpu::Adapter) -> Self {
+        Self {
+            info: adapter.get_info(),
+            features: adapter.features(),
+            limits: adapter.limits(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AdapterSelector {
+    pub backends: wgpu::Backends,
+    // case insensitive substring of the adapter name
+    pub name: Option<String>,
+    pub device_type: Option<wgpu::DeviceType>,
+    pub power_preference: wgpu::PowerPreference,
+    pub force_fallback_adapter: bool,
+    // if nothing matches, try the software adapter before giving up
+    pub software_fallback: bool,
+}
+
+impl Default for AdapterSelector {
+    fn default() -> Self {
+        Self {
+            backends: wgpu::Backends::all(),
+            name: None,
+            device_type: None,
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            software_fallback: false,
+        }
+    }
+}
+
+impl AdapterSelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // only ever pick the software adapter, mostly useful for tests
+    pub fn software() -> Self {
+        Self::default().force_fallback_adapter(true)
+    }
+
+    pub fn with_backends(mut self, backends: wgpu::Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn with_device_type(mut self, device_type: wgpu::DeviceType) -> Self {
+        self.device_type = Some(device_type);
+        self
+    }
+
+    pub fn with_power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    pub fn force_fallback_adapter(mut self, force_fallback_adapter: bool) -> Self {
+        self.force_fallback_adapter = force_fallback_adapter;
+        self
+    }
+
+    pub fn with_software_fallback(mut self, software_fallback: bool) -> Self {
+        self.software_fallback = software_fallback;
+        self
+    }
+
+    pub fn enumerate(instance: &wgpu::Instance, backends: wgpu::Backends) -> Vec<AdapterDescription> {
+        instance.enumerate_adapters(backends)
+            .map(|adapter| AdapterDescription::of(&adapter))
+            .collect()
+    }
+
+    pub fn matches(&self, info: &wgpu::AdapterInfo) -> bool {
+        if !self.backends.contains(wgpu::Backends::from(info.backend)) {
+            return false;
+        }
+        if let Some(name) = &self.name {
+            if !info.name.to_lowercase().contains(&name.to_lowercase()) {
+                return false;
+            }
+        }
+        if let Some(device_type) = self.device_type {
+            if info.device_type != device_type {
+                return false;
+            }
+        }
+        if self.force_fallback_adapter && info.device_type != wgpu::DeviceType::Cpu {
+            return false;
+        }
+        true
+    }
+
+    pub fn select(&self, instance: &wgpu::Instance, surface: Option<&wgpu::Surface>) -> Option<wgpu::Adapter> {
+        let selected = instance.enumerate_adapters(self.backends)
+            .filter(|adapter| self.matches(&adapter.get_info()))
+            .filter(|adapter| surface.is_none_or(|s| adapter.is_surface_supported(s)))
+            // min_by_key keeps the first of equal ranks, so enumeration order breaks ties
+            .min_by_key(|adapter| self.rank(adapter.get_info().device_type));
+
+        match selected {
+            Some(adapter) => Some(adapter),
+            None if self.software_fallback && !self.force_fallback_adapter => {
+                log::warn!("no adapter matched {:?}, falling back to software adapter", self);
+                Self { software_fallback: false, ..self.clone() }
+                    .force_fallback_adapter(true)
+                    .select(instance, surface)
+            },
+            None => None,
+        }
+    }
+
+    fn rank(&self, device_type: wgpu::DeviceType) -> u8 {
+        match (self.power_preference, device_type) {
+            (wgpu::PowerPreference::HighPerformance, wgpu::DeviceType::DiscreteGpu) => 0,
+            (wgpu::PowerPreference::HighPerformance, wgpu::DeviceType::IntegratedGpu) => 1,
+            (wgpu::PowerPreference::LowPower, wgpu::DeviceType::IntegratedGpu) => 0,
+            (wgpu::PowerPreference::LowPower, wgpu::DeviceType::DiscreteGpu) => 1,
+            (_, wgpu::DeviceType::VirtualGpu) => 2,
+            (_, wgpu::DeviceType::Other) => 3,
+            (_, wgpu::DeviceType::Cpu) => 4,
+        }
+    }
+}
@@ -1,28 +1,245 @@
 use crate::data::{ScreenSize, Vertex};
-use crate::text::{GlyphAtlas, Text2D};
-use crate::camera::{Camera, Camera3D, CameraController, CameraUniform};
+use crate::text::{GlyphAtlas, Text2D, TEXT2D_LAYOUT_ENTRIES};
+use crate::camera::{Camera, Camera3D, CameraController, CameraUniform, CAMERA3D_LAYOUT_ENTRIES};
 use crate::camera::fps_camera::CameraLegacy;
 
-use crate::pipeline::NamedPipeline;
+use crate::pipeline::{
+    BindGroupLayouts, BindSlot, ComputePipelineBuilder, NamedBindGroup, NamedComputePipeline,
+    NamedPipeline, PipelineBuilder, PipelineHandle, PipelineRegistry,
+};
+use crate::error::{BindError, PipelineError};
+use crate::target::RenderTarget;
+use crate::surface::SurfacePreferences;
+use crate::window::{SecondaryWindow, WindowOptions};
+use crate::time::{FrameClock, FrameTime};
+use crate::input::InputState;
+use crate::action::ActionMap;
+use crate::replay::{InputRecorder, Recording};
+use crate::preprocessor::ShaderLibrary;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
-    window::Window, dpi::PhysicalSize,
+    window::{Window, WindowId}, dpi::PhysicalSize,
 };
 
 use wgpu::util::DeviceExt;
 
 pub struct AppSkeleton {
-    pub window: Window,
-    pub event_loop: EventLoop<()>,
-    pub _instance: wgpu::Instance,
-    pub surface: wgpu::Surface,
+    // both are None for headless skeletons
+    pub window: Option<Window>,
+    pub event_loop: Option<EventLoop<()>>,
+    pub instance: wgpu::Instance,
+    pub target: RenderTarget,
     pub adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
     pub screen_size: ScreenSize,
+    pub surface_preferences: SurfacePreferences,
+    // extra windows opened through the SkeletonHandle, they share the device and queue
+    pub windows: HashMap<WindowId, SecondaryWindow>,
+    pub handle: SkeletonHandle,
+    pub clock: FrameClock,
+    pub run_mode: RunMode,
+    // timing of the last main window frame, secondary windows render with it
+    pub last_frame_time: FrameTime,
+    pub input: InputState,
+    pub actions: ActionMap,
+    pub recorder: Option<InputRecorder>,
+    // shared with every shader file so hot reloads resolve includes the same way
+    pub shader_library: Rc<ShaderLibrary>,
+}
+
+// requests the app can make from inside the event loop, they get applied by run() once the
+// current batch of events has been handled
+pub enum SkeletonRequest {
+    OpenWindow {
+        label: String,
+        options: WindowOptions,
+    },
+    CloseWindow(WindowId),
+    // swap in rebound actions, e.g. from a settings menu
+    SetActionMap(ActionMap),
+    RequestRedraw,
+    RedrawAt(Instant),
+    AnimateUntil(Instant),
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RunMode {
+    // redraw every window as fast as the present mode allows
+    #[default]
+    Continuous,
+    // sleep until there is input, a redraw request through the SkeletonHandle or a
+    // redraw deadline. for tools and editors that sit idle most of the time
+    Reactive,
+}
+
+#[derive(Clone, Default)]
+pub struct SkeletonHandle {
+    requests: Rc<RefCell<Vec<SkeletonRequest>>>,
+}
+
+impl SkeletonHandle {
+    pub fn open_window(&self, label: &str, options: WindowOptions) {
+        self.requests.borrow_mut().push(SkeletonRequest::OpenWindow {
+            label: label.to_string(),
+            options,
+        });
+    }
+
+    pub fn close_window(&self, window: WindowId) {
+        self.requests.borrow_mut().push(SkeletonRequest::CloseWindow(window));
+    }
+
+    pub fn set_action_map(&self, action_map: ActionMap) {
+        self.requests.borrow_mut().push(SkeletonRequest::SetActionMap(action_map));
+    }
+
+    // only does anything in RunMode::Reactive, continuous mode redraws every frame anyway
+    pub fn request_redraw(&self) {
+        self.requests.borrow_mut().push(SkeletonRequest::RequestRedraw);
+    }
+
+    pub fn request_redraw_at(&self, at: Instant) {
+        self.requests.borrow_mut().push(SkeletonRequest::RedrawAt(at));
+    }
+
+    // keeps redrawing every frame until the deadline passes
+    pub fn animate_until(&self, until: Instant) {
+        self.requests.borrow_mut().push(SkeletonRequest::AnimateUntil(until));
+    }
+
+    pub fn take_requests(&self) -> Vec<SkeletonRequest> {
+        self.requests.borrow_mut().drain(..).collect()
+    }
+}
+
+impl AppSkeleton {
+    pub fn is_headless(&self) -> bool {
+        self.window.is_none()
+    }
+
+    pub fn handle(&self) -> SkeletonHandle {
+        self.handle.clone()
+    }
+
+    pub fn set_fixed_timestep(&mut self, fixed_timestep: Option<Duration>) {
+        self.clock.fixed_timestep = fixed_timestep;
+    }
+
+    // starts recording from the next frame, replaces any recording in progress
+    pub fn start_recording(&mut self, recorder: InputRecorder) {
+        self.recorder = Some(recorder);
+    }
+
+    // stops recording and returns what was recorded, also saved if the recorder has a path
+    pub fn stop_recording(&mut self) -> Option<std::io::Result<Recording>> {
+        self.recorder.take().map(InputRecorder::finish)
+    }
+
+    // for registering shader snippets, shaders loaded before keep the library they were built with
+    pub fn shader_library_mut(&mut self) -> &mut ShaderLibrary {
+        Rc::make_mut(&mut self.shader_library)
+    }
+
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.config.width = size.width;
+        self.config.height = size.height;
+        self.screen_size.update(size.width, size.height);
+        self.target.configure(&self.device, &self.config);
+    }
+
+    // pipelines built against the old format need rebuilding after this
+    pub fn set_depth_format(&mut self, format: Option<wgpu::TextureFormat>) {
+        self.target.set_depth_format(&self.device, &self.config, format);
+    }
+
+    pub fn depth_format(&self) -> Option<wgpu::TextureFormat> {
+        self.target.depth_format()
+    }
+
+    pub fn supports_sample_count(&self, sample_count: u32) -> bool {
+        std::iter::once(self.config.format).chain(self.depth_format())
+            .all(|format| crate::target::sample_count_supported(&self.adapter, &self.device, format, sample_count))
+    }
+
+    // returns false (and leaves the target alone) if the count isn't supported. pipelines built
+    // for the old count need rebuilding after this
+    pub fn set_sample_count(&mut self, sample_count: u32) -> bool {
+        if !self.supports_sample_count(sample_count) {
+            log::warn!("{}x multisampling is not supported for the surface format", sample_count);
+            return false;
+        }
+        self.target.set_sample_count(&self.device, &self.config, sample_count);
+        true
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.target.sample_count
+    }
+
+    pub fn supported_present_modes(&self) -> Vec<wgpu::PresentMode> {
+        match self.target.surface() {
+            Some(surface) => surface.get_capabilities(&self.adapter).present_modes,
+            None => Vec::new(),
+        }
+    }
+
+    // returns false (and leaves the surface alone) if the surface can't present with that mode
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) -> bool {
+        if !self.supported_present_modes().contains(&present_mode) {
+            log::warn!("present_mode {:?} is not supported by the surface", present_mode);
+            return false;
+        }
+        self.config.present_mode = present_mode;
+        self.target.configure(&self.device, &self.config);
+        true
+    }
+
+    pub fn set_vsync(&mut self, enabled: bool) -> bool {
+        let present_mode = if enabled {
+            Some(wgpu::PresentMode::Fifo)
+        } else {
+            let supported = self.supported_present_modes();
+            [wgpu::PresentMode::Mailbox, wgpu::PresentMode::Immediate].into_iter()
+                .find(|m| supported.contains(m))
+        };
+        match present_mode {
+            Some(present_mode) => self.set_present_mode(present_mode),
+            None => false,
+        }
+    }
+}
+
+// anything that has to be rebuilt or rewritten when the main window changes size
+pub trait SizeDependent {
+    fn resize(&mut self, screen_size: &ScreenSize, device: &wgpu::Device, queue: &wgpu::Queue);
+}
+
+// pipelines run() rebuilds when their shader files change, see Application::hot_reload
+pub trait HotReload {
+    // returns how many pipelines were swapped
+    fn reload_changed(&mut self, device: &wgpu::Device) -> usize;
+}
+
+impl<T: HotReload> HotReload for Rc<RefCell<T>> {
+    fn reload_changed(&mut self, device: &wgpu::Device) -> usize {
+        self.borrow_mut().reload_changed(device)
+    }
+}
+
+// lets the app keep its own handle to a resource registered with App::add_size_dependent
+impl<T: SizeDependent> SizeDependent for Rc<RefCell<T>> {
+    fn resize(&mut self, screen_size: &ScreenSize, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.borrow_mut().resize(screen_size, device, queue);
+    }
 }
 
 pub trait Application: 'static + Sized {
@@ -43,38 +260,138 @@ pub trait Application: 'static + Sized {
         wgpu::Limits::default()
     }
     fn input(&mut self, event: &WindowEvent) -> bool;
-    fn update(&mut self, queue: &wgpu::Queue);
+    fn update(&mut self, queue: &wgpu::Queue, time: &FrameTime, input: &InputState, actions: &ActionMap);
+    // only called when the skeleton has a fixed timestep, as many times per frame as needed
+    // to catch up (see FrameTime::alpha for interpolating between steps)
+    fn fixed_update(
+        &mut self,
+        _queue: &wgpu::Queue,
+        _timestep: Duration,
+        _input: &InputState,
+        _actions: &ActionMap,
+    ) {}
+    // every frame before update, the only place that sees the whole skeleton while running.
+    // create or replace pipelines here (App::pipeline_builder needs the skeleton)
+    fn prepare(&mut self, _skeleton: &AppSkeleton, _time: &FrameTime) {}
+    // draw into target.current_frame(), target.surface() is the window surface (None for
+    // headless skeletons)
     fn render(
         &mut self,
-        surface: &wgpu::Surface,
+        target: &RenderTarget,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+        time: &FrameTime,
     ) -> Result<(), wgpu::SurfaceError>;
+
+    // secondary windows, see SkeletonHandle::open_window
+    fn window_opened(&mut self, _window: WindowId, _label: &str) {}
+    fn window_input(&mut self, _window: WindowId, _event: &WindowEvent) -> bool {
+        false
+    }
+    fn render_window(
+        &mut self,
+        _window: WindowId,
+        _target: &RenderTarget,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        _time: &FrameTime,
+    ) -> Result<(), wgpu::SurfaceError> {
+        Ok(())
+    }
+    fn window_closed(&mut self, _window: WindowId) {}
+
+    // what run() (and resize()) resize for you when the main window changes size. opt in with
+    // Some(&mut self.app) so the camera, text and everything added with add_size_dependent
+    // follow along, by default nothing is resized and on_resize is all you get
+    fn size_dependent(&mut self) -> Option<&mut dyn SizeDependent> {
+        None
+    }
+
+    // pipelines run() (and step()) reload at the start of every frame when their shader files
+    // changed, usually Some(&mut self.app) for the app's render and compute pipelines
+    fn hot_reload(&mut self) -> Option<&mut dyn HotReload> {
+        None
+    }
+
+    // lifecycle hooks, all of them are only called by run()
+    fn on_init(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue) {}
+    // after the surface has been reconfigured for the new size
+    fn on_resize(
+        &mut self,
+        _new_size: PhysicalSize<u32>,
+        _scale_factor: f64,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {}
+    fn on_focus_changed(&mut self, _focused: bool) {}
+    fn on_suspended(&mut self) {}
+    fn on_resumed(&mut self) {}
+    // return false to keep the app running (unsaved changes prompt, etc.)
+    fn on_close_requested(&mut self) -> bool {
+        true
+    }
+    fn on_exit(&mut self) {}
 }
 
 
-pub struct AppBuilder<'a, S> {
+pub struct AppBuilder<S> {
     pub skeleton: AppSkeleton,
     pub state: Option<S>,
 
     pub camera3d: Option<Camera3D<CameraLegacy>>,
     pub text2d: Option<Text2D>,
 
-    pub named_pipelines: Vec<NamedPipeline<'a>>,
+    pub pipelines: PipelineRegistry,
+    pub compute_pipelines: PipelineRegistry<NamedComputePipeline>,
+    pub bind_groups: HashMap<String, NamedBindGroup>,
+    pub size_dependents: Vec<Box<dyn SizeDependent>>,
 }
 
-impl<'a, S> AppBuilder<'a, S> {
+impl<S> AppBuilder<S> {
     pub fn state(mut self, state: S) -> Self {
         self.state = Some(state);
         self
     }
 
-    pub fn add_pipeline(mut self, pipeline: NamedPipeline<'a>) -> Self {
+    pub fn add_size_dependent(mut self, resource: Box<dyn SizeDependent>) -> Self {
+        self.size_dependents.push(resource);
+        self
+    }
+
+    pub fn pipeline_builder(&self, name: &str) -> PipelineBuilder<'_> {
+        NamedPipeline::named_for(name, &self.skeleton, self)
+    }
+
+    // panics if a pipeline with the same name was already added, try_add_pipeline returns the
+    // error instead
+    pub fn add_pipeline(self, pipeline: NamedPipeline) -> Self {
+        self.try_add_pipeline(pipeline).unwrap_or_else(|e| panic!("cannot add pipeline: {}!", e))
+    }
+
+    pub fn try_add_pipeline(mut self, pipeline: NamedPipeline) -> Result<Self, PipelineError> {
         // you need a skeleton and a camera3d (or text2d) to even create a NamedPipeline so i dont need
         // to explicitly check.
-        //let skeleton = self.skeleton.as_ref().expect("cannot add pipeline with no skeleton!");
-        //let camera = self.camera3d.as_ref().expect("cannot add pipeline with no camera!");
-        self.named_pipelines.push(pipeline);
+        self.pipelines.insert(pipeline)?;
+        Ok(self)
+    }
+
+    pub fn compute_pipeline_builder(&self, name: &str) -> ComputePipelineBuilder<'_> {
+        NamedComputePipeline::named_for(name, &self.skeleton, self)
+    }
+
+    // panics on duplicate names like add_pipeline
+    pub fn add_compute_pipeline(self, pipeline: NamedComputePipeline) -> Self {
+        self.try_add_compute_pipeline(pipeline).unwrap_or_else(|e| panic!("cannot add compute pipeline: {}!", e))
+    }
+
+    pub fn try_add_compute_pipeline(mut self, pipeline: NamedComputePipeline) -> Result<Self, PipelineError> {
+        self.compute_pipelines.insert(pipeline)?;
+        Ok(self)
+    }
+
+    // for BindSlot::Custom(name)
+    pub fn add_bind_group(mut self, name: &str, layout: wgpu::BindGroupLayout, bind_group: wgpu::BindGroup) -> Self {
+        self.bind_groups.insert(name.to_string(), NamedBindGroup { layout, bind_group });
         self
     }
 
@@ -98,18 +415,7 @@ impl<'a, S> AppBuilder<'a, S> {
 
         let bind_group_layout = self.skeleton.device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    }
-                ],
+                entries: CAMERA3D_LAYOUT_ENTRIES,
                 label: Some("camera_bind_group_layout"),
             }
         );
@@ -157,38 +463,7 @@ impl<'a, S> AppBuilder<'a, S> {
         );
         let bind_group_layout = self.skeleton.device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry { // screen metadata
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry { // text metadata
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer  {
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: None
-                        },
-                        count: None,
-                    }
-                ],
+                entries: TEXT2D_LAYOUT_ENTRIES,
                 label: Some("text_bind_group_layout"),
             }
         );
@@ -202,13 +477,14 @@ impl<'a, S> AppBuilder<'a, S> {
         self.text2d = Some(Text2D {
             vertex_buffer,
             bind_group_layout,
+            bind_group: None,
             glyph_atlas,
             screen_uniform_buffer,
         });
         self
     }
 
-    pub fn build(self) -> (AppSkeleton, App<'a, S>) {
+    pub fn build(self) -> (AppSkeleton, App<S>) {
         (
             self.skeleton,
             App {
@@ -217,14 +493,17 @@ impl<'a, S> AppBuilder<'a, S> {
                 camera3d: self.camera3d,
                 text2d: self.text2d,
 
-                named_pipelines: self.named_pipelines,
+                pipelines: self.pipelines,
+                compute_pipelines: self.compute_pipelines,
+                bind_groups: self.bind_groups,
+                size_dependents: self.size_dependents,
             }
         )
     }
 }
 
 
-pub struct App<'a, S> {
+pub struct App<S> {
     // owns the skeleton and should then ensure all the lifetimes are correct
     //skeleton: graphics::AppSkeleton,
     pub state: S,
@@ -232,20 +511,208 @@ pub struct App<'a, S> {
     pub camera3d: Option<Camera3D<CameraLegacy>>,
     pub text2d: Option<Text2D>,
 
-    pub named_pipelines: Vec<NamedPipeline<'a>>,
+    pub pipelines: PipelineRegistry,
+    pub compute_pipelines: PipelineRegistry<NamedComputePipeline>,
+    pub bind_groups: HashMap<String, NamedBindGroup>,
+    pub size_dependents: Vec<Box<dyn SizeDependent>>,
 }
 
-impl<'a, S> App<'_, S> {
+impl<S> App<S> {
     //fn builder() -> FigBuilder<'a> {
     //    FigBuilder::default()
     //}
-    pub fn for_skeleton(skeleton: AppSkeleton) -> AppBuilder<'a, S> {
+    pub fn for_skeleton(skeleton: AppSkeleton) -> AppBuilder<S> {
         AppBuilder {
             skeleton: skeleton,
             state: None,
             camera3d: None,
             text2d: None,
-            named_pipelines: Vec::<NamedPipeline>::default(),
+            pipelines: PipelineRegistry::new(),
+            compute_pipelines: PipelineRegistry::new(),
+            bind_groups: HashMap::new(),
+            size_dependents: Vec::new(),
+        }
+    }
+
+    pub fn add_size_dependent(&mut self, resource: Box<dyn SizeDependent>) {
+        self.size_dependents.push(resource);
+    }
+
+    // swaps the bind group registered under name, it has to match the layout it was added with
+    pub fn set_bind_group(&mut self, name: &str, bind_group: wgpu::BindGroup) {
+        match self.bind_groups.get_mut(name) {
+            Some(named) => named.bind_group = bind_group,
+            None => log::warn!("no bind group named {} to replace", name),
+        }
+    }
+
+    // for creating pipelines while running, the skeleton is needed for the device. see
+    // Application::prepare for where to get it
+    pub fn pipeline_builder<'r>(&'r self, name: &str, skeleton: &'r AppSkeleton) -> PipelineBuilder<'r> {
+        NamedPipeline::named_for(name, skeleton, self)
+    }
+
+    pub fn add_pipeline(&mut self, pipeline: NamedPipeline) -> Result<PipelineHandle, PipelineError> {
+        self.pipelines.insert(pipeline)
+    }
+
+    // swaps out the pipeline with the same name (or adds it), returns the old one
+    pub fn replace_pipeline(&mut self, pipeline: NamedPipeline) -> Option<NamedPipeline> {
+        self.pipelines.replace(pipeline).1
+    }
+
+    pub fn remove_pipeline(&mut self, name: &str) -> Option<NamedPipeline> {
+        self.pipelines.remove(name)
+    }
+
+    pub fn pipeline(&self, name: &str) -> Option<&NamedPipeline> {
+        self.pipelines.get(name)
+    }
+
+    pub fn compute_pipeline_builder<'r>(&'r self, name: &str, skeleton: &'r AppSkeleton) -> ComputePipelineBuilder<'r> {
+        NamedComputePipeline::named_for(name, skeleton, self)
+    }
+
+    pub fn add_compute_pipeline(&mut self, pipeline: NamedComputePipeline) -> Result<PipelineHandle, PipelineError> {
+        self.compute_pipelines.insert(pipeline)
+    }
+
+    pub fn replace_compute_pipeline(&mut self, pipeline: NamedComputePipeline) -> Option<NamedComputePipeline> {
+        self.compute_pipelines.replace(pipeline).1
+    }
+
+    pub fn remove_compute_pipeline(&mut self, name: &str) -> Option<NamedComputePipeline> {
+        self.compute_pipelines.remove(name)
+    }
+
+    pub fn compute_pipeline(&self, name: &str) -> Option<&NamedComputePipeline> {
+        self.compute_pipelines.get(name)
+    }
+
+    pub fn bind_group(&self, slot: &BindSlot) -> Option<&wgpu::BindGroup> {
+        match slot {
+            BindSlot::Camera3D => self.camera3d.as_ref().map(|camera| &camera.bind_group),
+            BindSlot::Text2D => self.text2d.as_ref().and_then(|text| text.bind_group.as_ref()),
+            BindSlot::GlyphAtlas => self.text2d.as_ref().map(|text| &text.glyph_atlas.glyph_bind_group),
+            BindSlot::Custom(name) => self.bind_groups.get(name).map(|named| &named.bind_group),
+            BindSlot::Manual => None,
+        }
+    }
+
+    // sets the pipeline and every bind group it was built with, Manual slots are left to the
+    // caller. nothing is set on the pass if a bind group is missing
+    pub fn bind_pipeline<'p>(&'p self, render_pass: &mut wgpu::RenderPass<'p>, name: &str) -> Result<(), BindError> {
+        let pipeline = self.pipeline(name).ok_or_else(|| BindError::UnknownPipeline(name.to_string()))?;
+        let bind_groups = self.slot_bind_groups(name, &pipeline.bind_slots)?;
+
+        render_pass.set_pipeline(&pipeline.pipeline);
+        for (index, bind_group) in bind_groups {
+            render_pass.set_bind_group(index, bind_group, &[]);
+        }
+        Ok(())
+    }
+
+    // sets the pipeline and the app's bind groups, Manual slots and push constants are up to the
+    // caller before dispatching
+    pub fn bind_compute_pipeline<'p>(&'p self, compute_pass: &mut wgpu::ComputePass<'p>, name: &str) -> Result<&'p NamedComputePipeline, BindError> {
+        let pipeline = self.compute_pipeline(name).ok_or_else(|| BindError::UnknownPipeline(name.to_string()))?;
+        let bind_groups = self.slot_bind_groups(name, &pipeline.bind_slots)?;
+
+        compute_pass.set_pipeline(&pipeline.pipeline);
+        for (index, bind_group) in bind_groups {
+            compute_pass.set_bind_group(index, bind_group, &[]);
+        }
+        Ok(pipeline)
+    }
+
+    // binds and runs one invocation per element, workgroups are sized from the shader. Manual
+    // bind groups can be set on the pass beforehand, with push constants use
+    // bind_compute_pipeline and NamedComputePipeline::dispatch instead
+    pub fn dispatch<'p>(&'p self, compute_pass: &mut wgpu::ComputePass<'p>, name: &str, elements: u32) -> Result<(), BindError> {
+        self.dispatch_3d(compute_pass, name, [elements, 1, 1])
+    }
+
+    // one invocation per pixel, for image processing
+    pub fn dispatch_2d<'p>(&'p self, compute_pass: &mut wgpu::ComputePass<'p>, name: &str, width: u32, height: u32) -> Result<(), BindError> {
+        self.dispatch_3d(compute_pass, name, [width, height, 1])
+    }
+
+    pub fn dispatch_3d<'p>(&'p self, compute_pass: &mut wgpu::ComputePass<'p>, name: &str, elements: [u32; 3]) -> Result<(), BindError> {
+        self.bind_compute_pipeline(compute_pass, name)?.dispatch(compute_pass, elements)
+    }
+
+    // everything the pipeline needs bound that isn't Manual, by bind group index
+    fn slot_bind_groups(&self, name: &str, bind_slots: &[BindSlot]) -> Result<Vec<(u32, &wgpu::BindGroup)>, BindError> {
+        let mut bind_groups = Vec::with_capacity(bind_slots.len());
+        for (index, slot) in bind_slots.iter().enumerate() {
+            if *slot == BindSlot::Manual {
+                continue;
+            }
+            let bind_group = self.bind_group(slot).ok_or_else(|| BindError::MissingBindGroup {
+                pipeline: name.to_string(),
+                slot: slot.clone(),
+            })?;
+            bind_groups.push((index as u32, bind_group));
+        }
+        Ok(bind_groups)
+    }
+
+    pub fn draw<'p>(
+        &'p self,
+        render_pass: &mut wgpu::RenderPass<'p>,
+        name: &str,
+        vertices: std::ops::Range<u32>,
+        instances: std::ops::Range<u32>,
+    ) -> Result<(), BindError> {
+        self.bind_pipeline(render_pass, name)?;
+        render_pass.draw(vertices, instances);
+        Ok(())
+    }
+}
+
+impl<S> BindGroupLayouts for AppBuilder<S> {
+    fn bind_group_layout(&self, slot: &BindSlot) -> Option<&wgpu::BindGroupLayout> {
+        slot_layout(slot, self.camera3d.as_ref(), self.text2d.as_ref(), &self.bind_groups)
+    }
+}
+
+impl<S> BindGroupLayouts for App<S> {
+    fn bind_group_layout(&self, slot: &BindSlot) -> Option<&wgpu::BindGroupLayout> {
+        slot_layout(slot, self.camera3d.as_ref(), self.text2d.as_ref(), &self.bind_groups)
+    }
+}
+
+fn slot_layout<'r>(
+    slot: &BindSlot,
+    camera3d: Option<&'r Camera3D<CameraLegacy>>,
+    text2d: Option<&'r Text2D>,
+    bind_groups: &'r HashMap<String, NamedBindGroup>,
+) -> Option<&'r wgpu::BindGroupLayout> {
+    match slot {
+        BindSlot::Camera3D => camera3d.map(|camera| &camera.bind_group_layout),
+        BindSlot::Text2D => text2d.map(|text| &text.bind_group_layout),
+        BindSlot::GlyphAtlas => text2d.map(|text| &text.glyph_atlas.glyph_bind_group_layout),
+        BindSlot::Custom(name) => bind_groups.get(name).map(|named| &named.layout),
+        BindSlot::Manual => None,
+    }
+}
+
+impl<S> HotReload for App<S> {
+    fn reload_changed(&mut self, device: &wgpu::Device) -> usize {
+        self.pipelines.reload_changed(device) + self.compute_pipelines.reload_changed(device)
+    }
+}
+
+impl<S> SizeDependent for App<S> {
+    fn resize(&mut self, screen_size: &ScreenSize, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(camera3d) = self.camera3d.as_mut() {
+            camera3d.resize(screen_size, device, queue);
+        }
+        if let Some(text2d) = self.text2d.as_mut() {
+            text2d.resize(screen_size, device, queue);
+        }
+        for resource in self.size_dependents.iter_mut() {
+            resource.resize(screen_size, device, queue);
         }
     }
 }
\ No newline at end of file
@@ -23,4 +23,8 @@ impl Camera for CameraLegacy {
         let proj = Mat4::perspective_rh(self.fovy, self.aspect, self.znear, self.zfar);
         proj * view
     }
+
+    fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+    }
 }
\ No newline at end of file
@@ -2,6 +2,10 @@ pub mod fps_camera;
 pub mod orbit_camera;
 
 use fps_camera::CameraLegacy;
+use crate::app::SizeDependent;
+use crate::data::ScreenSize;
+use crate::input::InputState;
+use crate::action::{ActionMap, CAMERA_FORWARD_AXIS, CAMERA_RIGHT_AXIS};
 
 use cgmath::EuclideanSpace;
 use glam::{Mat4, Vec3};
@@ -16,6 +20,21 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     0.0, 0.0, 0.5, 1.0,
 );
 
+// the camera uniform at binding 0, used by add_camera3d and checked against shaders by
+// PipelineBuilder::layout_from_reflection
+pub const CAMERA3D_LAYOUT_ENTRIES: &[wgpu::BindGroupLayoutEntry] = &[
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+];
+
 pub struct Camera3D<C: Camera> {
     pub camera: C,
     pub uniform: CameraUniform,
@@ -27,6 +46,16 @@ pub struct Camera3D<C: Camera> {
 
 pub trait Camera {
     fn build_view_projection_matrix(&self) -> Mat4;
+    fn set_aspect(&mut self, aspect: f32);
+}
+
+impl<C: Camera> SizeDependent for Camera3D<C> {
+    fn resize(&mut self, screen_size: &ScreenSize, _device: &wgpu::Device, queue: &wgpu::Queue) {
+        let [width, height] = *screen_size.size();
+        self.camera.set_aspect(width as f32 / height as f32);
+        self.uniform.update_view_proj(&self.camera);
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    }
 }
 
 
@@ -48,7 +77,7 @@ impl CameraUniform {
         }
     }
 
-    pub fn update_view_proj(&mut self, camera: &CameraLegacy) {
+    pub fn update_view_proj<C: Camera>(&mut self, camera: &C) {
         self.view_proj = camera.build_view_projection_matrix().to_cols_array_2d();
     }
 }
@@ -110,6 +139,16 @@ impl CameraController {
         }
     }
 
+    // reads action::CAMERA_FORWARD_AXIS and CAMERA_RIGHT_AXIS, arrow keys by default
+    pub fn update_from_input(&mut self, input: &InputState, actions: &ActionMap) {
+        let forward = actions.axis(CAMERA_FORWARD_AXIS, input);
+        let right = actions.axis(CAMERA_RIGHT_AXIS, input);
+        self.is_forward_pressed = forward > 0.0;
+        self.is_backward_pressed = forward < 0.0;
+        self.is_right_pressed = right > 0.0;
+        self.is_left_pressed = right < 0.0;
+    }
+
     pub fn update_camera(&self, camera: &mut CameraLegacy) {
         /*
         use cgmath::InnerSpace;
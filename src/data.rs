@@ -53,3 +53,45 @@ impl Vertex {
         }
     }
 }
+
+// per instance model matrix and color, goes in a second vertex buffer next to the Vertex one.
+// locations 1-4 are the matrix columns and 5 the color
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Instance {
+    model: [[f32; 4]; 4],
+    color: [f32; 4],
+}
+
+impl Instance {
+    pub fn new(model: glam::Mat4, color: [f32; 4]) -> Self {
+        Self {
+            model: model.to_cols_array_2d(),
+            color,
+        }
+    }
+
+    pub fn model(&self) -> glam::Mat4 {
+        glam::Mat4::from_cols_array_2d(&self.model)
+    }
+
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        1 => Float32x4,
+        2 => Float32x4,
+        3 => Float32x4,
+        4 => Float32x4,
+        5 => Float32x4,
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum GraphicsInitError {
+    NoAdapter,
+    MissingFeatures(wgpu::Features),
+    InsufficientShaderModel {
+        required: wgpu::ShaderModel,
+        supported: wgpu::ShaderModel,
+    },
+    MissingDownlevelFlags(wgpu::DownlevelFlags),
+    WindowCreation(winit::error::OsError),
+    InvalidIcon(winit::window::BadIcon),
+    SurfaceCreation(wgpu::CreateSurfaceError),
+    // the adapter can't present to the surface at all (no formats reported)
+    IncompatibleSurface,
+    // the adapter can't multisample the surface (or depth) format with this many samples
+    UnsupportedSampleCount(u32),
+    // build_headless with a 0 width or height, wgpu can't create empty textures
+    InvalidHeadlessSize {
+        width: u32,
+        height: u32,
+    },
+    DeviceRequest(wgpu::RequestDeviceError),
+}
+
+impl fmt::Display for GraphicsInitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphicsInitError::NoAdapter =>
+                write!(f, "no suitable graphics adapter found"),
+            GraphicsInitError::MissingFeatures(features) =>
+                write!(f, "adapter does not support required features: {:?}", features),
+            GraphicsInitError::InsufficientShaderModel { required, supported } =>
+                write!(f, "adapter supports shader model {:?}, but {:?} is required", supported, required),
+            GraphicsInitError::MissingDownlevelFlags(flags) =>
+                write!(f, "adapter does not support the downlevel capabilities required: {:?}", flags),
+            GraphicsInitError::WindowCreation(e) =>
+                write!(f, "failed to create window: {}", e),
+            GraphicsInitError::InvalidIcon(e) =>
+                write!(f, "invalid window icon: {}", e),
+            GraphicsInitError::SurfaceCreation(e) =>
+                write!(f, "failed to create surface: {}", e),
+            GraphicsInitError::IncompatibleSurface =>
+                write!(f, "adapter is not compatible with the window surface"),
+            GraphicsInitError::UnsupportedSampleCount(count) =>
+                write!(f, "adapter does not support {}x multisampling for the surface format", count),
+            GraphicsInitError::InvalidHeadlessSize { width, height } =>
+                write!(f, "cannot create a {}x{} headless target", width, height),
+            GraphicsInitError::DeviceRequest(e) =>
+                write!(f, "adapter device creation failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for GraphicsInitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GraphicsInitError::WindowCreation(e) => Some(e),
+            GraphicsInitError::InvalidIcon(e) => Some(e),
+            GraphicsInitError::SurfaceCreation(e) => Some(e),
+            GraphicsInitError::DeviceRequest(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<winit::error::OsError> for GraphicsInitError {
+    fn from(e: winit::error::OsError) -> Self {
+        GraphicsInitError::WindowCreation(e)
+    }
+}
+
+impl From<wgpu::CreateSurfaceError> for GraphicsInitError {
+    fn from(e: wgpu::CreateSurfaceError) -> Self {
+        GraphicsInitError::SurfaceCreation(e)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for GraphicsInitError {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        GraphicsInitError::DeviceRequest(e)
+    }
+}
+
+// problems with a pipeline description that would otherwise only show up as a wgpu validation
+// panic (or not at all)
+#[derive(Clone, Debug)]
+pub enum PipelineError {
+    // a pipeline with that name is already registered
+    DuplicateName(String),
+    Shader(ShaderError),
+    MissingShader(String),
+    // with_define after the shader methods, the shader was already preprocessed without it
+    DefineAfterShader {
+        pipeline: String,
+        define: String,
+    },
+    // two vertex buffers (or two attributes of one) feed the same @location
+    ShaderLocationConflict {
+        pipeline: String,
+        location: u32,
+    },
+    MissingEntryPoint {
+        pipeline: String,
+        entry_point: String,
+        stage: naga::ShaderStage,
+        // what the module does have for that stage
+        available: Vec<String>,
+    },
+    // depth only pipelines have nothing to write to without depth
+    DepthOnlyWithoutDepth(String),
+    // with_depth_bias without with_depth, there is nothing to bias
+    DepthBiasWithoutDepth(String),
+    // stencil ops set, but the depth format has no stencil (or there is no depth at all)
+    MissingStencilAspect(String),
+    // a blend preset other than Replace on a color target whose format can't blend
+    FormatNotBlendable {
+        pipeline: String,
+        target: usize,
+        format: wgpu::TextureFormat,
+    },
+    // strip_index_format is only allowed with strip topologies
+    StripIndexFormatWithoutStrip(String),
+    // wgpu rejected the pipeline (shader stages that don't fit together or the layout)
+    Validation {
+        pipeline: String,
+        message: String,
+    },
+    // features the pipeline uses that the device wasn't created with (see Application::optional_features)
+    MissingFeatures {
+        pipeline: String,
+        features: wgpu::Features,
+    },
+    // layout_from_reflection or with_reflected_vertex_layout couldn't make sense of the shader
+    Reflection {
+        pipeline: String,
+        message: String,
+    },
+    // the shader's @group doesn't fit the layout of the bind slot it was given
+    BindGroupMismatch {
+        pipeline: String,
+        group: u32,
+        slot: crate::pipeline::BindSlot,
+        message: String,
+    },
+    // a vertex shader @location the vertex buffer layouts don't feed (or feed the wrong type)
+    VertexInputMismatch {
+        pipeline: String,
+        location: u32,
+        message: String,
+    },
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::DuplicateName(pipeline) =>
+                write!(f, "a pipeline named {} already exists", pipeline),
+            PipelineError::Shader(e) =>
+                write!(f, "{}", e),
+            PipelineError::MissingShader(pipeline) =>
+                write!(f, "cannot build {} pipeline without shader", pipeline),
+            PipelineError::DefineAfterShader { pipeline, define } =>
+                write!(f, "{} pipeline: define {} was added after the shader, call with_define before the shader methods", pipeline, define),
+            PipelineError::ShaderLocationConflict { pipeline, location } =>
+                write!(f, "{} pipeline: vertex shader location {} is used by more than one attribute", pipeline, location),
+            PipelineError::MissingEntryPoint { pipeline, entry_point, stage, available } =>
+                write!(f, "{} pipeline: shader has no {:?} entry point named {} (available: {})",
+                    pipeline, stage, entry_point, available.join(", ")),
+            PipelineError::DepthOnlyWithoutDepth(pipeline) =>
+                write!(f, "cannot build depth only {} pipeline without depth", pipeline),
+            PipelineError::DepthBiasWithoutDepth(pipeline) =>
+                write!(f, "{} pipeline sets a depth bias without depth", pipeline),
+            PipelineError::MissingStencilAspect(pipeline) =>
+                write!(f, "{} pipeline uses stencil, but its depth format has no stencil aspect", pipeline),
+            PipelineError::FormatNotBlendable { pipeline, target, format } =>
+                write!(f, "{} pipeline: color target {} is {:?}, which can't blend (use BlendPreset::Replace)", pipeline, target, format),
+            PipelineError::StripIndexFormatWithoutStrip(pipeline) =>
+                write!(f, "{} pipeline sets a strip index format without a strip topology", pipeline),
+            PipelineError::Validation { pipeline, message } =>
+                write!(f, "{} pipeline failed validation: {}", pipeline, message),
+            PipelineError::MissingFeatures { pipeline, features } =>
+                write!(f, "{} pipeline needs device features that were not requested: {:?}", pipeline, features),
+            PipelineError::Reflection { pipeline, message } =>
+                write!(f, "cannot reflect {} pipeline: {}", pipeline, message),
+            PipelineError::BindGroupMismatch { pipeline, group, slot, message } =>
+                write!(f, "{} pipeline: @group({}) does not match {:?}: {}", pipeline, group, slot, message),
+            PipelineError::VertexInputMismatch { pipeline, location, message } =>
+                write!(f, "{} pipeline: vertex input @location({}): {}", pipeline, location, message),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PipelineError::Shader(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ShaderError> for PipelineError {
+    fn from(e: ShaderError) -> Self {
+        PipelineError::Shader(e)
+    }
+}
+
+// loading a shader file failed, kept as strings so the error can be stored and cloned
+#[derive(Clone, Debug)]
+pub enum ShaderError {
+    Io {
+        path: std::path::PathBuf,
+        message: String,
+    },
+    // a bad #include, #define or #ifdef, line is in file (which may be an include)
+    Preprocess {
+        file: String,
+        line: u32,
+        message: String,
+    },
+    // naga's diagnostic, with the offending source lines
+    Compile {
+        path: String,
+        diagnostic: String,
+    },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::Io { path, message } =>
+                write!(f, "failed to read shader {}: {}", path.display(), message),
+            ShaderError::Preprocess { file, line, message } =>
+                write!(f, "failed to preprocess shader {}:{}: {}", file, line, message),
+            ShaderError::Compile { path, diagnostic } =>
+                write!(f, "failed to compile shader {}:\n{}", path, diagnostic),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+// App::bind_pipeline couldn't find something it needed
+#[derive(Debug)]
+pub enum BindError {
+    UnknownPipeline(String),
+    MissingBindGroup {
+        pipeline: String,
+        slot: crate::pipeline::BindSlot,
+    },
+    // a dispatch that needs more workgroups on an axis than the device allows
+    TooManyWorkgroups {
+        pipeline: String,
+        workgroups: [u32; 3],
+        max: u32,
+    },
+}
+
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindError::UnknownPipeline(pipeline) =>
+                write!(f, "no pipeline named {}", pipeline),
+            BindError::MissingBindGroup { pipeline, slot } =>
+                write!(f, "{} pipeline: no bind group for {:?}", pipeline, slot),
+            BindError::TooManyWorkgroups { pipeline, workgroups, max } =>
+                write!(f, "{} pipeline: cannot dispatch {:?} workgroups, the device allows {} per dimension", pipeline, workgroups, max),
+        }
+    }
+}
+
+impl std::error::Error for BindError {}
@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+
+use glam::Vec2;
+use winit::event::{
+    DeviceEvent, ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+    VirtualKeyCode, WindowEvent,
+};
+
+// roughly how many pixels one line of scrolling is, so line and pixel deltas can be summed
+const PIXELS_PER_LINE: f32 = 20.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum InputButton {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+impl From<VirtualKeyCode> for InputButton {
+    fn from(key: VirtualKeyCode) -> Self {
+        InputButton::Key(key)
+    }
+}
+
+impl From<MouseButton> for InputButton {
+    fn from(button: MouseButton) -> Self {
+        InputButton::Mouse(button)
+    }
+}
+
+// keyboard and mouse state, kept up to date by run(). the just_* queries and deltas cover
+// everything since the last frame
+#[derive(Clone, Debug, Default)]
+pub struct InputState {
+    down: HashSet<InputButton>,
+    pressed: HashSet<InputButton>,
+    released: HashSet<InputButton>,
+    cursor_position: Option<Vec2>,
+    cursor_delta: Vec2,
+    mouse_motion: Vec2,
+    // in lines, y is positive when scrolling away from the user
+    scroll_delta: Vec2,
+    modifiers: ModifiersState,
+    // the last button event was a key repeat
+    repeat: bool,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn process_window_event(&mut self, event: &WindowEvent) {
+        self.repeat = false;
+        match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state,
+                    virtual_keycode: Some(keycode),
+                    ..
+                },
+                ..
+            } => self.set_button(InputButton::Key(*keycode), *state),
+            WindowEvent::MouseInput { state, button, .. } => self.set_button(InputButton::Mouse(*button), *state),
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = Vec2::new(position.x as f32, position.y as f32);
+                if let Some(last) = self.cursor_position {
+                    self.cursor_delta += position - last;
+                }
+                self.cursor_position = Some(position);
+            },
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(x, y) => self.scroll_delta += Vec2::new(*x, *y),
+                MouseScrollDelta::PixelDelta(position) => {
+                    self.scroll_delta += Vec2::new(position.x as f32, position.y as f32) / PIXELS_PER_LINE;
+                },
+            },
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+            // we never get the release events for anything held while focus is gone
+            WindowEvent::Focused(false) => self.release_all(),
+            _ => {}
+        }
+    }
+
+    pub fn process_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta: (x, y) } = event {
+            self.mouse_motion += Vec2::new(*x as f32, *y as f32);
+        }
+    }
+
+    // clears the per-frame state, run() calls this after every frame
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.cursor_delta = Vec2::ZERO;
+        self.mouse_motion = Vec2::ZERO;
+        self.scroll_delta = Vec2::ZERO;
+    }
+
+    pub fn is_pressed<B: Into<InputButton>>(&self, button: B) -> bool {
+        self.down.contains(&button.into())
+    }
+
+    pub fn just_pressed<B: Into<InputButton>>(&self, button: B) -> bool {
+        self.pressed.contains(&button.into())
+    }
+
+    pub fn just_released<B: Into<InputButton>>(&self, button: B) -> bool {
+        self.released.contains(&button.into())
+    }
+
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.cursor_position
+    }
+
+    pub fn cursor_delta(&self) -> Vec2 {
+        self.cursor_delta
+    }
+
+    // raw device motion, keeps working while the cursor is grabbed or hidden
+    pub fn mouse_motion(&self) -> Vec2 {
+        self.mouse_motion
+    }
+
+    pub fn scroll_delta(&self) -> Vec2 {
+        self.scroll_delta
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    // whether the last window event was another Pressed for something already held, which is
+    // how key repeat shows up
+    pub fn is_repeat(&self) -> bool {
+        self.repeat
+    }
+
+    fn set_button(&mut self, button: InputButton, state: ElementState) {
+        match state {
+            // key repeat sends more Pressed events, only the first one counts
+            ElementState::Pressed => if self.down.insert(button) {
+                self.pressed.insert(button);
+            } else {
+                self.repeat = true;
+            },
+            ElementState::Released => if self.down.remove(&button) {
+                self.released.insert(button);
+            },
+        }
+    }
+
+    fn release_all(&mut self) {
+        self.released.extend(self.down.drain());
+        self.modifiers = ModifiersState::empty();
+    }
+}
@@ -3,61 +3,234 @@ pub mod vector;
 pub mod text;
 pub mod app;
 pub mod pipeline;
+pub mod shader;
+pub mod preprocessor;
+pub mod reflect;
+pub mod storage;
+pub mod target;
+pub mod error;
+pub mod adapter;
+pub mod surface;
+pub mod window;
+pub mod time;
+pub mod input;
+pub mod action;
+pub mod replay;
 
 pub mod camera;
 
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::Window, dpi::PhysicalSize,
 };
 
+use error::GraphicsInitError;
+
 
 // ====== application build ======
 
-pub async fn build<E: app::Application>(title: &str) -> app::AppSkeleton {
-    // required by wgpu
-    env_logger::init();
+pub struct SkeletonBuilder {
+    pub window_options: window::WindowOptions,
+    pub adapter_selector: adapter::AdapterSelector,
+    pub surface_preferences: surface::SurfacePreferences,
+    pub fixed_timestep: Option<Duration>,
+    pub run_mode: app::RunMode,
+    pub action_map: action::ActionMap,
+    pub record_input: Option<std::path::PathBuf>,
+    pub depth_format: Option<wgpu::TextureFormat>,
+    pub sample_count: u32,
+    pub shader_library: preprocessor::ShaderLibrary,
+}
+
+impl SkeletonBuilder {
+    pub fn new(title: &str) -> Self {
+        Self {
+            window_options: window::WindowOptions::new(title),
+            adapter_selector: adapter::AdapterSelector::default(),
+            surface_preferences: surface::SurfacePreferences::default(),
+            fixed_timestep: None,
+            run_mode: app::RunMode::default(),
+            action_map: action::ActionMap::with_defaults(),
+            record_input: None,
+            depth_format: None,
+            sample_count: 1,
+            shader_library: preprocessor::ShaderLibrary::new(),
+        }
+    }
+
+    pub fn with_window_options(mut self, window_options: window::WindowOptions) -> Self {
+        self.window_options = window_options;
+        self
+    }
+
+    pub fn with_adapter_selector(mut self, adapter_selector: adapter::AdapterSelector) -> Self {
+        self.adapter_selector = adapter_selector;
+        self
+    }
+
+    // calls Application::fixed_update at a fixed rate, independent of the frame rate
+    pub fn with_fixed_timestep(mut self, fixed_timestep: Duration) -> Self {
+        self.fixed_timestep = Some(fixed_timestep);
+        self
+    }
+
+    // replaces the default map, leave out action::QUIT_ACTION to never quit from input
+    pub fn with_action_map(mut self, action_map: action::ActionMap) -> Self {
+        self.action_map = action_map;
+        self
+    }
+
+    // records every input event and frame time, written to path when the event loop exits.
+    // play it back with replay::Recording::load and replay::replay
+    pub fn with_input_recording<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self {
+        self.record_input = Some(path.into());
+        self
+    }
+
+    // what #include in shaders can find, defaults to the built-in fox/ snippets
+    pub fn with_shader_library(mut self, shader_library: preprocessor::ShaderLibrary) -> Self {
+        self.shader_library = shader_library;
+        self
+    }
+
+    // gives the main target (and secondary windows) a depth texture, kept at the target's size
+    pub fn with_depth(mut self, format: wgpu::TextureFormat) -> Self {
+        self.depth_format = Some(format);
+        self
+    }
+
+    // msaa for the main target and secondary windows, building fails if the adapter can't
+    // multisample the surface or depth format with this count
+    pub fn with_sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    pub fn with_run_mode(mut self, run_mode: app::RunMode) -> Self {
+        self.run_mode = run_mode;
+        self
+    }
+
+    pub fn with_surface_preferences(mut self, surface_preferences: surface::SurfacePreferences) -> Self {
+        self.surface_preferences = surface_preferences;
+        self
+    }
 
-    // create window
-    let (window, event_loop) = create_window(title);
+    pub async fn build<E: app::Application>(self) -> Result<app::AppSkeleton, GraphicsInitError> {
+        // required by wgpu
+        let _ = env_logger::try_init();
 
-    // get GPU handle
-    let instance = get_gpu_instance();
+        // create window
+        let (window, event_loop) = create_window(&self.window_options)?;
 
-    // create surface
-    let surface = create_surface(&window, &instance).unwrap();
+        // get GPU handle
+        let instance = get_gpu_instance();
 
-    // request adapter
-    let (adapter, device, queue) = request_adapter::<E>(&instance, &surface).await;
+        // create surface
+        let surface = create_surface(&window, &instance)?;
 
-    let size = window.inner_size();
-    let screen_size = data::ScreenSize::new(size.width, size.height);
-    let config = create_surface_configuration(&surface, &adapter, &size);
-    surface.configure(&device, &config);
+        // request adapter
+        let (adapter, device, queue) = request_adapter::<E>(&instance, Some(&surface), &self.adapter_selector).await?;
 
-    app::AppSkeleton {
-        window,
-        event_loop,
-        _instance: instance,
-        surface,
-        adapter: adapter,
-        device,
-        queue,
-        config,
-        screen_size,
+        let size = window.inner_size();
+        let screen_size = data::ScreenSize::new(size.width, size.height);
+        let config = create_surface_configuration(&surface, &adapter, &size, &self.surface_preferences)?;
+        surface.configure(&device, &config);
+        let mut target = target::RenderTarget::from_surface(surface);
+        target.set_depth_format(&device, &config, self.depth_format);
+        if self.sample_count > 1 {
+            check_sample_count(&adapter, &device, &config, self.depth_format, self.sample_count)?;
+            target.set_sample_count(&device, &config, self.sample_count);
+        }
+
+        Ok(app::AppSkeleton {
+            window: Some(window),
+            event_loop: Some(event_loop),
+            instance,
+            target,
+            adapter,
+            device,
+            queue,
+            config,
+            screen_size,
+            surface_preferences: self.surface_preferences,
+            windows: HashMap::new(),
+            handle: app::SkeletonHandle::default(),
+            clock: time::FrameClock::new(self.fixed_timestep),
+            run_mode: self.run_mode,
+            last_frame_time: time::FrameTime::default(),
+            input: input::InputState::default(),
+            actions: self.action_map.clone(),
+            recorder: self.record_input.map(replay::InputRecorder::to_file),
+            shader_library: std::rc::Rc::new(self.shader_library),
+        })
+    }
+
+    // builds a skeleton that renders into an offscreen texture instead of a window surface, use
+    // step() to drive frames
+    pub async fn build_headless<E: app::Application>(self, width: u32, height: u32) -> Result<app::AppSkeleton, GraphicsInitError> {
+        let _ = env_logger::try_init();
+        if width == 0 || height == 0 {
+            return Err(GraphicsInitError::InvalidHeadlessSize { width, height });
+        }
+
+        let instance = get_gpu_instance();
+
+        let (adapter, device, queue) = request_adapter::<E>(&instance, None, &self.adapter_selector).await?;
+
+        let screen_size = data::ScreenSize::new(width, height);
+        let config = create_offscreen_configuration(width, height, &self.surface_preferences);
+        let mut target = target::RenderTarget::from_offscreen(target::OffscreenTexture::new(&device, &config));
+        target.set_depth_format(&device, &config, self.depth_format);
+        if self.sample_count > 1 {
+            check_sample_count(&adapter, &device, &config, self.depth_format, self.sample_count)?;
+            target.set_sample_count(&device, &config, self.sample_count);
+        }
+
+        Ok(app::AppSkeleton {
+            window: None,
+            event_loop: None,
+            instance,
+            target,
+            adapter,
+            device,
+            queue,
+            config,
+            screen_size,
+            surface_preferences: self.surface_preferences,
+            windows: HashMap::new(),
+            handle: app::SkeletonHandle::default(),
+            clock: time::FrameClock::new(self.fixed_timestep),
+            run_mode: self.run_mode,
+            last_frame_time: time::FrameTime::default(),
+            input: input::InputState::default(),
+            actions: self.action_map.clone(),
+            recorder: self.record_input.map(replay::InputRecorder::to_file),
+            shader_library: std::rc::Rc::new(self.shader_library),
+        })
     }
 }
 
-fn create_window(title: &str) -> (Window, EventLoop<()>) {
+pub async fn build<E: app::Application>(title: &str) -> Result<app::AppSkeleton, GraphicsInitError> {
+    SkeletonBuilder::new(title).build::<E>().await
+}
+
+// headless skeletons fall back to the software adapter so they work in ci and on render servers
+pub async fn build_headless<E: app::Application>(width: u32, height: u32) -> Result<app::AppSkeleton, GraphicsInitError> {
+    SkeletonBuilder::new("headless")
+        .with_adapter_selector(adapter::AdapterSelector::default().with_software_fallback(true))
+        .build_headless::<E>(width, height).await
+}
+
+fn create_window(options: &window::WindowOptions) -> Result<(Window, EventLoop<()>), GraphicsInitError> {
     let event_loop = EventLoop::new();
-    (
-        winit::window::WindowBuilder::new()
-            .with_title(title)
-            .build(&event_loop)
-            .unwrap(),
-        event_loop
-    )
+    let window = options.window_builder(&event_loop)?
+        .build(&event_loop)?;
+    Ok((window, event_loop))
 }
 
 fn get_gpu_instance() -> wgpu::Instance {
@@ -75,41 +248,40 @@ fn create_surface(window: &Window, instance: &wgpu::Instance) ->
     }
 }
 
-async fn request_adapter<E: app::Application>(instance: &wgpu::Instance, surface: &wgpu::Surface) ->
-    (wgpu::Adapter, wgpu::Device, wgpu::Queue)
+async fn request_adapter<E: app::Application>(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface>,
+    selector: &adapter::AdapterSelector,
+) -> Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue), GraphicsInitError>
 {
-    let adapter = instance.request_adapter(
-        &wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::default(),
-            compatible_surface: Some(&surface),
-            force_fallback_adapter: false,
-        },
-    ).await.unwrap();
-    
+    for description in adapter::AdapterSelector::enumerate(instance, selector.backends) {
+        log::debug!("available adapter: {:?}", description.info);
+    }
+    let adapter = selector.select(instance, surface).ok_or(GraphicsInitError::NoAdapter)?;
+
     // print adapter info
     let adapter_info = adapter.get_info();
-    println!("Attempting adapter: {} ({:?})", adapter_info.name, adapter_info.backend);
+    log::info!("Attempting adapter: {} ({:?})", adapter_info.name, adapter_info.backend);
 
     let required_features = E::required_features();
     let adapter_features = adapter.features();
-    assert!(
-        adapter_features.contains(required_features),
-        "Adapter does not support required features: {:?}",
-        required_features - adapter_features
-    );
+    if !adapter_features.contains(required_features) {
+        return Err(GraphicsInitError::MissingFeatures(required_features - adapter_features));
+    }
 
     let required_downlevel_capabilities = E::required_downlevel_capabilities();
     let downlevel_capabilities = adapter.get_downlevel_capabilities();
-    assert!(
-        downlevel_capabilities.shader_model >= required_downlevel_capabilities.shader_model,
-        "Adapter does not support the minimum shader model required: {:?}",
-        required_downlevel_capabilities.shader_model
-    );
-    assert!(
-        downlevel_capabilities.flags.contains(required_downlevel_capabilities.flags),
-        "Adapter does not support the downlevel capabilities required: {:?}",
-        required_downlevel_capabilities.flags - downlevel_capabilities.flags
-    );
+    if downlevel_capabilities.shader_model < required_downlevel_capabilities.shader_model {
+        return Err(GraphicsInitError::InsufficientShaderModel {
+            required: required_downlevel_capabilities.shader_model,
+            supported: downlevel_capabilities.shader_model,
+        });
+    }
+    if !downlevel_capabilities.flags.contains(required_downlevel_capabilities.flags) {
+        return Err(GraphicsInitError::MissingDownlevelFlags(
+            required_downlevel_capabilities.flags - downlevel_capabilities.flags
+        ));
+    }
 
     let trace_dir = std::env::var("WGPU_TRACE");
     let (device, queue) = adapter.request_device(
@@ -119,107 +291,360 @@ async fn request_adapter<E: app::Application>(instance: &wgpu::Instance, surface
             label: None,
         },
         trace_dir.ok().as_ref().map(std::path::Path::new),
-    ).await
-    .expect("Adapter device creation failed!");
+    ).await?;
 
-    (adapter, device, queue)
+    Ok((adapter, device, queue))
 }
 
 fn create_surface_configuration(
     surface: &wgpu::Surface,
     adapter: &wgpu::Adapter,
-    size: &PhysicalSize<u32>
-) -> wgpu::SurfaceConfiguration {
+    size: &PhysicalSize<u32>,
+    preferences: &surface::SurfacePreferences,
+) -> Result<wgpu::SurfaceConfiguration, GraphicsInitError> {
     let surface_capabilities = surface.get_capabilities(&adapter);
+    if surface_capabilities.formats.is_empty() {
+        return Err(GraphicsInitError::IncompatibleSurface);
+    }
 
-    let surface_format = surface_capabilities.formats.iter()
-        .copied()
-        .find(|f| f.is_srgb())
-        .unwrap_or(surface_capabilities.formats[0]);
-
-    // attempt to use Mailbox present_mode, otherwise fallback to Fifo (VSync)
-    println!("avail present_modes: {:?}", surface_capabilities.present_modes);
-    let surface_present_mode = surface_capabilities.present_modes.iter()
-        .copied()
-        .find(|m| m == &wgpu::PresentMode::Mailbox)
-        .unwrap_or(wgpu::PresentMode::Fifo);
-    println!("using present_mode: {:?}", surface_present_mode);
+    let surface_format = preferences.choose_format(&surface_capabilities);
+    let surface_present_mode = preferences.choose_present_mode(&surface_capabilities);
+    let surface_alpha_mode = preferences.choose_alpha_mode(&surface_capabilities);
+    log::debug!("surface capabilities: {:?}", surface_capabilities);
+    log::info!(
+        "using surface format: {:?}, present_mode: {:?}, alpha_mode: {:?}",
+        surface_format, surface_present_mode, surface_alpha_mode
+    );
 
     let surface_view_format = surface_format.add_srgb_suffix();
 
-    wgpu::SurfaceConfiguration {
+    Ok(wgpu::SurfaceConfiguration {
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
         format: surface_format,
         width: size.width,
         height: size.height,
         present_mode: surface_present_mode,
-        alpha_mode: surface_capabilities.alpha_modes[0],
+        alpha_mode: surface_alpha_mode,
         view_formats: vec![surface_view_format],
+    })
+}
+
+fn check_sample_count(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    depth_format: Option<wgpu::TextureFormat>,
+    sample_count: u32,
+) -> Result<(), GraphicsInitError> {
+    let supported = std::iter::once(config.format).chain(depth_format)
+        .all(|format| target::sample_count_supported(adapter, device, format, sample_count));
+    if supported {
+        Ok(())
+    } else {
+        Err(GraphicsInitError::UnsupportedSampleCount(sample_count))
+    }
+}
+
+fn create_offscreen_configuration(
+    width: u32,
+    height: u32,
+    preferences: &surface::SurfacePreferences,
+) -> wgpu::SurfaceConfiguration {
+    // not used to configure a real surface, but pipelines and cameras read their format and
+    // size from it so keep one around for offscreen rendering too
+    let format = preferences.formats.first()
+        .copied()
+        .unwrap_or(wgpu::TextureFormat::Rgba8UnormSrgb);
+    wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format,
+        width,
+        height,
+        present_mode: wgpu::PresentMode::Fifo,
+        alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+        view_formats: vec![format],
     }
 }
 
 // ====== applicaiton run ======
 
 pub fn run<E: app::Application>(mut app: E, mut skeleton: app::AppSkeleton) {
+    let event_loop = skeleton.event_loop.take()
+        .expect("cannot run a headless skeleton! (hint: use step() instead)");
+    let main_window_id = skeleton.window.as_ref()
+        .expect("cannot run a skeleton without a window!")
+        .id();
+
+    // the first frame always gets drawn, even in reactive mode
+    let mut redraw = RedrawSchedule {
+        pending: true,
+        ..Default::default()
+    };
+
     log::info!("Entering event loop...");
-    skeleton.event_loop.run(move |event, _, control_flow| {
+    event_loop.run(move |event, event_loop_target, control_flow| {
+        // any window event (input, resize, focus...) is worth a redraw in reactive mode
+        if let Event::WindowEvent { .. } = event {
+            redraw.pending = true;
+        }
+
+        // input state is tracked for the main window, even if the app consumes the event
+        match &event {
+            Event::WindowEvent { event, window_id } if *window_id == main_window_id => {
+                skeleton.input.process_window_event(event);
+                if let Some(recorder) = &mut skeleton.recorder {
+                    recorder.record_window_event(skeleton.clock.frame(), event);
+                }
+            },
+            Event::DeviceEvent { event, .. } => {
+                skeleton.input.process_device_event(event);
+                if let Some(recorder) = &mut skeleton.recorder {
+                    recorder.record_device_event(skeleton.clock.frame(), event);
+                }
+            },
+            _ => {}
+        }
+
         match event {
             Event::WindowEvent {
                 ref event,
                 window_id,
-            } if window_id == skeleton.window.id() => if !app.input(event) {
+            } if window_id == main_window_id => {
+                // events the app consumed are done with
+                let consumed = app.input(event);
                 match event {
-                    WindowEvent::CloseRequested
-                    | WindowEvent::KeyboardInput {
-                        input:
-                            KeyboardInput {
-                                state: ElementState::Pressed,
-                                virtual_keycode: Some(VirtualKeyCode::Escape),
-                                ..
-                            },
-                        ..
-                    } => *control_flow = ControlFlow::Exit,
-                    WindowEvent::Resized(pysical_size) => {
-                        if resize_possible(pysical_size) {
-                            //size = *pysical_size;
-                            skeleton.config.width = pysical_size.width;
-                            skeleton.config.height = pysical_size.height;
-                            skeleton.surface.configure(&skeleton.device, &skeleton.config);
-                        }
+                    _ if consumed => {},
+                    WindowEvent::CloseRequested if app.on_close_requested() => {
+                        *control_flow = ControlFlow::Exit;
+                    },
+                    WindowEvent::KeyboardInput { .. } | WindowEvent::MouseInput { .. }
+                        if skeleton.actions.matches_event(action::QUIT_ACTION, event, &skeleton.input)
+                            && app.on_close_requested() =>
+                    {
+                        *control_flow = ControlFlow::Exit;
+                    },
+                    WindowEvent::Resized(pysical_size) if resize_possible(pysical_size) => {
+                        let scale_factor = skeleton.window.as_ref().map_or(1.0, |w| w.scale_factor());
+                        resize(&mut app, &mut skeleton, *pysical_size, scale_factor);
                     },
-                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                        // gotta deference it twice cause apparently its &&mut
-                        if resize_possible(*&new_inner_size) {
-                            //size = **new_inner_size;
-                            skeleton.config.width = new_inner_size.width;
-                            skeleton.config.height = new_inner_size.height;
-                            skeleton.surface.configure(&skeleton.device, &skeleton.config);
-                        }
+                    // new_inner_size is a &&mut
+                    WindowEvent::ScaleFactorChanged { new_inner_size, scale_factor } if resize_possible(new_inner_size) => {
+                        resize(&mut app, &mut skeleton, **new_inner_size, *scale_factor);
                     },
+                    WindowEvent::Focused(focused) => app.on_focus_changed(*focused),
                     _ => {}
                 }
             },
-            Event::RedrawRequested(window_id) if window_id == skeleton.window.id() => {
-                app.update(&skeleton.queue);
-                match app.render(&skeleton.surface, &skeleton.device, &skeleton.queue) {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if skeleton.windows.contains_key(&window_id) => {
+                let consumed = app.window_input(window_id, event);
+                match event {
+                    _ if consumed => {},
+                    // closing a secondary window never exits the app
+                    WindowEvent::CloseRequested => {
+                        skeleton.close_window(window_id);
+                        app.window_closed(window_id);
+                    },
+                    WindowEvent::Resized(pysical_size) if resize_possible(pysical_size) => {
+                        skeleton.resize_window(window_id, *pysical_size);
+                    },
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } if resize_possible(new_inner_size) => {
+                        skeleton.resize_window(window_id, **new_inner_size);
+                    },
+                    _ => {}
+                }
+            },
+            // reactive mode slept until now, that's not frame time
+            Event::NewEvents(StartCause::WaitCancelled { .. } | StartCause::ResumeTimeReached { .. }) => {
+                skeleton.clock.wake();
+            },
+            Event::NewEvents(StartCause::Init) => {
+                app.on_init(&skeleton.device, &skeleton.queue);
+                // dont count build and setup time as the first frame
+                skeleton.clock.reset();
+            },
+            Event::Suspended => app.on_suspended(),
+            Event::Resumed => app.on_resumed(),
+            Event::LoopDestroyed => {
+                app.on_exit();
+                if let Some(recorder) = skeleton.recorder.take() {
+                    if let Err(e) = recorder.finish() {
+                        log::error!("Failed to save input recording: {}", e);
+                    }
+                }
+            },
+            Event::RedrawRequested(window_id) if window_id == main_window_id => {
+                match step(&mut app, &mut skeleton) {
                     Ok(_) => {},
                     // reconfigure surface if lost
-                    Err(wgpu::SurfaceError::Lost) => skeleton.surface.configure(&skeleton.device, &skeleton.config),
+                    Err(wgpu::SurfaceError::Lost) => skeleton.target.configure(&skeleton.device, &skeleton.config),
                     // out of memory, attempt reset
                     Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
                     // ignore other errors, they'll probably be gone by the next frame
                     Err(e) => eprintln!("{:?}", e),
                 };
             },
+            Event::RedrawRequested(window_id) if skeleton.windows.contains_key(&window_id) => {
+                let secondary = &skeleton.windows[&window_id];
+                match app.render_window(window_id, &secondary.target, &skeleton.device, &skeleton.queue, &skeleton.last_frame_time) {
+                    Ok(_) => {},
+                    Err(wgpu::SurfaceError::Lost) => {
+                        let secondary = skeleton.windows.get_mut(&window_id).unwrap();
+                        secondary.target.configure(&skeleton.device, &secondary.config);
+                    },
+                    Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
+                    Err(e) => eprintln!("{:?}", e),
+                };
+            },
             Event::RedrawEventsCleared => {
-                // manually trigger RedrawRequested otherwise it only triggers once
-                skeleton.window.request_redraw();
+                // handled here rather than MainEventsCleared so requests made during
+                // update/render are seen before the loop goes to sleep
+                for request in skeleton.handle.take_requests() {
+                    match request {
+                        app::SkeletonRequest::OpenWindow { label, options } => {
+                            match skeleton.open_window(event_loop_target, &label, &options) {
+                                Ok(window_id) => app.window_opened(window_id, &label),
+                                Err(e) => log::error!("failed to open window {}: {}", label, e),
+                            }
+                        },
+                        app::SkeletonRequest::CloseWindow(window_id) => {
+                            if skeleton.close_window(window_id).is_some() {
+                                app.window_closed(window_id);
+                            }
+                        },
+                        app::SkeletonRequest::SetActionMap(action_map) => skeleton.actions = action_map,
+                        app::SkeletonRequest::RequestRedraw => redraw.pending = true,
+                        app::SkeletonRequest::RedrawAt(at) => redraw.request_at(at),
+                        app::SkeletonRequest::AnimateUntil(until) => redraw.animate_until(until),
+                    }
+                }
+
+                let redraw_now = match skeleton.run_mode {
+                    app::RunMode::Continuous => true,
+                    app::RunMode::Reactive => redraw.due(Instant::now()),
+                };
+                if redraw_now {
+                    // manually trigger RedrawRequested otherwise it only triggers once
+                    if let Some(window) = &skeleton.window {
+                        window.request_redraw();
+                    }
+                    for secondary in skeleton.windows.values() {
+                        secondary.window.request_redraw();
+                    }
+                }
+
+                // exit can't be undone, dont even try
+                if !matches!(*control_flow, ControlFlow::ExitWithCode(_)) {
+                    *control_flow = match skeleton.run_mode {
+                        app::RunMode::Continuous => ControlFlow::Poll,
+                        app::RunMode::Reactive => redraw.control_flow(),
+                    };
+                }
             }
             _ => {}
         }
     });
 }
 
-fn resize_possible(size: &PhysicalSize<u32>) -> bool {
+// runs a single update + render, this is what run() does every frame and what headless
+// skeletons use instead of an event loop
+pub fn step<E: app::Application>(app: &mut E, skeleton: &mut app::AppSkeleton) -> Result<(), wgpu::SurfaceError> {
+    let time = skeleton.clock.tick();
+    step_frame(app, skeleton, time)
+}
+
+// same as step() but advances the clock by exactly delta, for deterministic headless stepping
+pub fn step_with_delta<E: app::Application>(
+    app: &mut E,
+    skeleton: &mut app::AppSkeleton,
+    delta: Duration,
+) -> Result<(), wgpu::SurfaceError> {
+    let time = skeleton.clock.advance(delta);
+    step_frame(app, skeleton, time)
+}
+
+fn step_frame<E: app::Application>(
+    app: &mut E,
+    skeleton: &mut app::AppSkeleton,
+    time: time::FrameTime,
+) -> Result<(), wgpu::SurfaceError> {
+    if let Some(timestep) = skeleton.clock.fixed_timestep {
+        for _ in 0..time.fixed_steps {
+            app.fixed_update(&skeleton.queue, timestep, &skeleton.input, &skeleton.actions);
+        }
+    }
+    if let Some(recorder) = &mut skeleton.recorder {
+        recorder.record_frame(time.frame, time.delta);
+    }
+    skeleton.last_frame_time = time;
+    if let Some(pipelines) = app.hot_reload() {
+        pipelines.reload_changed(&skeleton.device);
+    }
+    app.prepare(skeleton, &time);
+    app.update(&skeleton.queue, &time, &skeleton.input, &skeleton.actions);
+    let result = app.render(&skeleton.target, &skeleton.device, &skeleton.queue, &time);
+    skeleton.input.end_frame();
+    result
+}
+
+// resizes the main target and everything the app registered as size dependent, headless
+// skeletons can call this directly
+pub fn resize<E: app::Application>(
+    app: &mut E,
+    skeleton: &mut app::AppSkeleton,
+    size: PhysicalSize<u32>,
+    scale_factor: f64,
+) {
+    skeleton.resize(size);
+    if let Some(resources) = app.size_dependent() {
+        resources.resize(&skeleton.screen_size, &skeleton.device, &skeleton.queue);
+    }
+    app.on_resize(size, scale_factor, &skeleton.device, &skeleton.queue);
+}
+
+#[derive(Default)]
+struct RedrawSchedule {
+    pending: bool,
+    at: Option<Instant>,
+    animate_until: Option<Instant>,
+}
+
+impl RedrawSchedule {
+    fn request_at(&mut self, at: Instant) {
+        self.at = Some(self.at.map_or(at, |current| current.min(at)));
+    }
+
+    fn animate_until(&mut self, until: Instant) {
+        self.animate_until = Some(self.animate_until.map_or(until, |current| current.max(until)));
+    }
+
+    // whether to redraw right now, consumes whatever requests were due
+    fn due(&mut self, now: Instant) -> bool {
+        let mut due = std::mem::take(&mut self.pending);
+        if self.at.is_some_and(|at| at <= now) {
+            self.at = None;
+            due = true;
+        }
+        match self.animate_until {
+            Some(until) if until > now => due = true,
+            _ => self.animate_until = None,
+        }
+        due
+    }
+
+    fn control_flow(&self) -> ControlFlow {
+        if self.animate_until.is_some() {
+            ControlFlow::Poll
+        } else if let Some(at) = self.at {
+            ControlFlow::WaitUntil(at)
+        } else {
+            ControlFlow::Wait
+        }
+    }
+}
+
+pub(crate) fn resize_possible(size: &PhysicalSize<u32>) -> bool {
     size.width > 0 && size.height > 0
 }
@@ -1,56 +1,520 @@
-use crate::app;
 use crate::data::Vertex;
-use crate::app::{AppBuilder, AppSkeleton, Application};
+use crate::app::AppSkeleton;
+use crate::error::{BindError, PipelineError, ShaderError};
+use crate::shader::{Shader, ShaderFile, ShaderWatch};
+use crate::preprocessor::Defines;
+use crate::reflect::{self, ReflectedLayout};
 
-pub struct NamedPipeline<'a> {
-    pub name: &'a str,
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// what sits at a bind group index, so App::bind_pipeline can find the matching bind group
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BindSlot {
+    Camera3D,
+    // text metadata, needs Text2D::bind_group
+    Text2D,
+    GlyphAtlas,
+    // registered with AppBuilder::add_bind_group
+    Custom(String),
+    // from use_custom_bind_group_layouts, the render code binds these itself
+    Manual,
+}
+
+impl BindSlot {
+    // the layout the app builds for the slot, None for slots whose layout isn't known up front
+    pub fn layout_entries(&self) -> Option<&'static [wgpu::BindGroupLayoutEntry]> {
+        match self {
+            BindSlot::Camera3D => Some(crate::camera::CAMERA3D_LAYOUT_ENTRIES),
+            BindSlot::Text2D => Some(crate::text::TEXT2D_LAYOUT_ENTRIES),
+            BindSlot::GlyphAtlas => Some(crate::text::GLYPH_ATLAS_LAYOUT_ENTRIES),
+            BindSlot::Custom(_) | BindSlot::Manual => None,
+        }
+    }
+}
+
+// a bind group registered on the app under a name, for BindSlot::Custom
+pub struct NamedBindGroup {
+    pub layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+// anything that can hand out the layouts for bind slots, AppBuilder and App both do
+pub trait BindGroupLayouts {
+    fn bind_group_layout(&self, slot: &BindSlot) -> Option<&wgpu::BindGroupLayout>;
+}
+
+pub struct NamedPipeline {
+    pub name: String,
     pub pipeline: wgpu::RenderPipeline,
-    // store some information about what bind groups im using and index they are at
-    // this is necessary in the render function to tell the encoder the right bind groups
+    // bind group index -> what to bind there
+    pub bind_slots: Vec<BindSlot>,
+    pub recipe: PipelineRecipe,
 }
 
-impl NamedPipeline<'_> {
-    pub fn named_for<'a, S>(name: &'a str, app: AppBuilder<'a, S>) -> PipelineBuilder<'a, S> {
-        // device: &'a wgpu::Device
+impl NamedPipeline {
+    // see also AppBuilder::pipeline_builder and App::pipeline_builder
+    pub fn named_for<'r>(name: &str, skeleton: &'r AppSkeleton, layouts: &'r dyn BindGroupLayouts) -> PipelineBuilder<'r> {
         PipelineBuilder {
-            app,
-            name,
-            shader: None,
+            skeleton,
+            layouts,
+            name: name.to_string(),
+            vertex_shader: None,
+            fragment_shader: None,
+            shader_error: None,
+            defines: Defines::new(),
+            late_define: None,
+            reflection_error: None,
+            vertex_entry_point: "vs_main".to_string(),
+            fragment_entry_point: "fs_main".to_string(),
             pipeline_layout: None,
-            vertex_buffer_layout: Some(Vertex::desc()),
+            bind_slots: Vec::new(),
+            vertex_buffer_layouts: vec![Vertex::desc().into()],
             primitive_topology: wgpu::PrimitiveTopology::TriangleList,
             front_face_format: wgpu::FrontFace::Ccw,
             cull_mode: Some(wgpu::Face::Back),
             polygon_mode: wgpu::PolygonMode::Fill,
+            depth_stencil: None,
+            depth_bias: wgpu::DepthBiasState::default(),
+            stencil: wgpu::StencilState::default(),
+            strip_index_format: None,
+            unclipped_depth: false,
+            conservative: false,
+            depth_only: false,
+            sample_count: None,
+            alpha_to_coverage: false,
+            color_targets: vec![ColorTarget::surface()],
+        }
+    }
+}
+
+// ====== pipeline registry ======
+
+// stays valid until the pipeline is removed, replacing keeps the handle. removed slots get
+// reused, the generation tells an old handle apart from the pipeline that took its slot
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineHandle {
+    index: usize,
+    generation: u32,
+}
+
+// what the registry needs from a pipeline, NamedPipeline and NamedComputePipeline both do
+pub trait RegistryPipeline {
+    fn name(&self) -> &str;
+    // None if nothing changed on disk, on errors the pipeline stays as it was
+    fn reload_if_changed(&mut self, device: &wgpu::Device) -> Option<Result<(), PipelineError>>;
+}
+
+impl RegistryPipeline for NamedPipeline {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn reload_if_changed(&mut self, device: &wgpu::Device) -> Option<Result<(), PipelineError>> {
+        self.recipe.rebuild_if_changed(device).map(|rebuilt| rebuilt.map(|(recipe, pipeline)| {
+            self.recipe = recipe;
+            self.pipeline = pipeline;
+        }))
+    }
+}
+
+// render pipelines by default, PipelineRegistry<NamedComputePipeline> for compute
+pub struct PipelineRegistry<P = NamedPipeline> {
+    slots: Vec<RegistrySlot<P>>,
+    // slots of removed pipelines, the next insert reuses them
+    free: Vec<usize>,
+    names: HashMap<String, PipelineHandle>,
+}
+
+struct RegistrySlot<P> {
+    // bumped on every remove so old handles stop matching
+    generation: u32,
+    pipeline: Option<P>,
+}
+
+impl<P> Default for PipelineRegistry<P> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            names: HashMap::new(),
+        }
+    }
+}
+
+impl<P: RegistryPipeline> PipelineRegistry<P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, pipeline: P) -> Result<PipelineHandle, PipelineError> {
+        if self.names.contains_key(pipeline.name()) {
+            return Err(PipelineError::DuplicateName(pipeline.name().to_string()));
+        }
+        let name = pipeline.name().to_string();
+        let handle = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.pipeline = Some(pipeline);
+                PipelineHandle { index, generation: slot.generation }
+            },
+            None => {
+                self.slots.push(RegistrySlot { generation: 0, pipeline: Some(pipeline) });
+                PipelineHandle { index: self.slots.len() - 1, generation: 0 }
+            },
+        };
+        self.names.insert(name, handle);
+        Ok(handle)
+    }
+
+    // swaps in a pipeline under the same name (or adds it), returns the old one
+    pub fn replace(&mut self, pipeline: P) -> (PipelineHandle, Option<P>) {
+        match self.names.get(pipeline.name()) {
+            Some(&handle) => (handle, self.slots[handle.index].pipeline.replace(pipeline)),
+            None => (self.insert(pipeline).unwrap(), None),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<P> {
+        let handle = self.names.remove(name)?;
+        let slot = &mut self.slots[handle.index];
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        slot.pipeline.take()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&P> {
+        self.names.get(name).and_then(|handle| self.get_by_handle(*handle))
+    }
+
+    pub fn get_by_handle(&self, handle: PipelineHandle) -> Option<&P> {
+        self.slots.get(handle.index)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.pipeline.as_ref())
+    }
+
+    pub fn handle(&self, name: &str) -> Option<PipelineHandle> {
+        self.names.get(name).copied()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains_key(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &P> {
+        self.slots.iter().filter_map(|slot| slot.pipeline.as_ref())
+    }
+
+    // rebuilds every pipeline whose shader files changed since they were loaded, call it once a
+    // frame or so. pipelines that fail to rebuild keep the last good version and the error is
+    // logged. returns how many pipelines were swapped
+    pub fn reload_changed(&mut self, device: &wgpu::Device) -> usize {
+        let mut reloaded = 0;
+        for pipeline in self.slots.iter_mut().filter_map(|slot| slot.pipeline.as_mut()) {
+            match pipeline.reload_if_changed(device) {
+                Some(Ok(())) => {
+                    log::info!("reloaded {} pipeline", pipeline.name());
+                    reloaded += 1;
+                },
+                Some(Err(e)) => log::error!("failed to reload {} pipeline: {}", pipeline.name(), e),
+                None => {},
+            }
+        }
+        reloaded
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum BlendPreset {
+    // no blending, overwrites whatever is there
+    #[default]
+    Replace,
+    // straight (non premultiplied) alpha, the usual choice for text and sprites
+    Alpha,
+    PremultipliedAlpha,
+    // adds color weighted by alpha, for glows and particles
+    Additive,
+    // multiplies with what is there, for tinting and shadows
+    Multiply,
+    Custom(wgpu::BlendState),
+}
+
+impl BlendPreset {
+    pub fn blend_state(&self) -> wgpu::BlendState {
+        match self {
+            BlendPreset::Replace => wgpu::BlendState::REPLACE,
+            BlendPreset::Alpha => wgpu::BlendState::ALPHA_BLENDING,
+            BlendPreset::PremultipliedAlpha => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            BlendPreset::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            },
+            BlendPreset::Multiply => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Dst,
+                    dst_factor: wgpu::BlendFactor::Zero,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+            BlendPreset::Custom(blend) => *blend,
+        }
+    }
+}
+
+// a wgpu::VertexBufferLayout that owns its attributes, so layouts made at runtime (see
+// with_reflected_vertex_layout) don't need to be 'static
+#[derive(Clone, Debug, PartialEq)]
+pub struct OwnedVertexBufferLayout {
+    pub array_stride: wgpu::BufferAddress,
+    pub step_mode: wgpu::VertexStepMode,
+    pub attributes: Vec<wgpu::VertexAttribute>,
+}
+
+impl OwnedVertexBufferLayout {
+    pub fn as_layout(&self) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: self.array_stride,
+            step_mode: self.step_mode,
+            attributes: &self.attributes,
+        }
+    }
+}
+
+impl From<wgpu::VertexBufferLayout<'_>> for OwnedVertexBufferLayout {
+    fn from(layout: wgpu::VertexBufferLayout<'_>) -> Self {
+        Self {
+            array_stride: layout.array_stride,
+            step_mode: layout.step_mode,
+            attributes: layout.attributes.to_vec(),
         }
     }
 }
 
-pub struct PipelineBuilder<'a, S> {
-    //device: &'a wgpu::Device,
-    pub app: AppBuilder<'a, S>,
-    pub name: &'a str,
-    pub shader: Option<wgpu::ShaderModule>,
+// one color output of a pipeline, matches @location(n) in the fragment shader
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColorTarget {
+    // None means the skeleton's surface format
+    pub format: Option<wgpu::TextureFormat>,
+    pub blend: BlendPreset,
+    pub write_mask: wgpu::ColorWrites,
+}
+
+impl ColorTarget {
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            format: Some(format),
+            ..Self::surface()
+        }
+    }
+
+    pub fn surface() -> Self {
+        Self {
+            format: None,
+            blend: BlendPreset::default(),
+            write_mask: wgpu::ColorWrites::ALL,
+        }
+    }
+
+    pub fn with_blend(mut self, blend: BlendPreset) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    pub fn with_write_mask(mut self, write_mask: wgpu::ColorWrites) -> Self {
+        self.write_mask = write_mask;
+        self
+    }
+
+    fn state(&self, surface_format: wgpu::TextureFormat) -> wgpu::ColorTargetState {
+        wgpu::ColorTargetState {
+            format: self.format.unwrap_or(surface_format),
+            // no blend state at all for Replace, formats that can't blend (float32, integers)
+            // reject even that
+            blend: (self.blend != BlendPreset::Replace).then(|| self.blend.blend_state()),
+            write_mask: self.write_mask,
+        }
+    }
+}
+
+pub struct PipelineBuilder<'r> {
+    pub skeleton: &'r AppSkeleton,
+    pub layouts: &'r dyn BindGroupLayouts,
+    pub name: String,
+    pub vertex_shader: Option<Rc<Shader>>,
+    pub fragment_shader: Option<Rc<Shader>>,
+    // from the shader methods, reported by validate
+    pub shader_error: Option<ShaderError>,
+    // for the preprocessor, only seen by shaders added after them
+    pub defines: Defines,
+    // the first define that came after a shader was already compiled, reported by validate
+    pub late_define: Option<String>,
+    // from the reflection methods, reported by validate
+    pub reflection_error: Option<PipelineError>,
+    pub vertex_entry_point: String,
+    pub fragment_entry_point: String,
     pub pipeline_layout: Option<wgpu::PipelineLayout>,
-    pub vertex_buffer_layout: Option<wgpu::VertexBufferLayout<'static>>,
+    pub bind_slots: Vec<BindSlot>,
+    // one per vertex buffer slot, in slot order
+    pub vertex_buffer_layouts: Vec<OwnedVertexBufferLayout>,
     pub primitive_topology: wgpu::PrimitiveTopology,
     pub front_face_format: wgpu::FrontFace,
     pub cull_mode: Option<wgpu::Face>,
     pub polygon_mode: wgpu::PolygonMode,
+    pub depth_stencil: Option<wgpu::DepthStencilState>,
+    pub depth_bias: wgpu::DepthBiasState,
+    pub stencil: wgpu::StencilState,
+    pub strip_index_format: Option<wgpu::IndexFormat>,
+    // needs Features::DEPTH_CLIP_CONTROL
+    pub unclipped_depth: bool,
+    // needs Features::CONSERVATIVE_RASTERIZATION
+    pub conservative: bool,
+    // no fragment stage or color targets, only writes depth (shadow maps, depth prepass)
+    pub depth_only: bool,
+    // None picks one from the targets, see with_sample_count
+    pub sample_count: Option<u32>,
+    // only does anything with msaa
+    pub alpha_to_coverage: bool,
+    pub color_targets: Vec<ColorTarget>,
 }
 
-impl<'a, S> PipelineBuilder<'a, S> {
+impl PipelineBuilder<'_> {
+    // same as #define NAME value at the top of every shader, use "" for a plain #ifdef flag.
+    // has to come before the shader methods, validate reports the ones that don't
+    pub fn with_define(mut self, name: &str, value: &str) -> Self {
+        if self.vertex_shader.is_some() || self.fragment_shader.is_some() || self.shader_error.is_some() {
+            self.late_define.get_or_insert_with(|| name.to_string());
+        }
+        self.defines.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    // one module for both stages, wgsl goes through the preprocessor first
     pub fn with_shader(mut self, source: wgpu::ShaderSource) -> Self {
-        let device = &self.app.skeleton.device;
-        self.shader = Some(device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some(format!("{} shader", self.name).as_str()),
-            source: source.into(),
-        }));
+        if let Some(shader) = self.create_shader(format!("{} shader", self.name), source) {
+            self.vertex_shader = Some(shader.clone());
+            self.fragment_shader = Some(shader);
+        }
+        self
+    }
+
+    pub fn with_vertex_shader(mut self, source: wgpu::ShaderSource) -> Self {
+        if let Some(shader) = self.create_shader(format!("{} vertex shader", self.name), source) {
+            self.vertex_shader = Some(shader);
+        }
+        self
+    }
+
+    pub fn with_fragment_shader(mut self, source: wgpu::ShaderSource) -> Self {
+        if let Some(shader) = self.create_shader(format!("{} fragment shader", self.name), source) {
+            self.fragment_shader = Some(shader);
+        }
+        self
+    }
+
+    fn create_shader(&mut self, label: String, source: wgpu::ShaderSource) -> Option<Rc<Shader>> {
+        compile_shader(self.skeleton, &self.defines, &label, source)
+            .map_err(|e| self.shader_error = Some(e))
+            .ok()
+    }
+
+    // wgsl from disk, PipelineRegistry::reload_changed rebuilds the pipeline when it (or anything
+    // it includes) changes. takes a path or a ShaderFile with its own defines
+    pub fn with_shader_file<F: Into<ShaderFile>>(mut self, file: F) -> Self {
+        if let Some(shader) = self.load_shader_file(file) {
+            self.vertex_shader = Some(shader.clone());
+            self.fragment_shader = Some(shader);
+        }
         self
     }
 
+    pub fn with_vertex_shader_file<F: Into<ShaderFile>>(mut self, file: F) -> Self {
+        if let Some(shader) = self.load_shader_file(file) {
+            self.vertex_shader = Some(shader);
+        }
+        self
+    }
+
+    pub fn with_fragment_shader_file<F: Into<ShaderFile>>(mut self, file: F) -> Self {
+        if let Some(shader) = self.load_shader_file(file) {
+            self.fragment_shader = Some(shader);
+        }
+        self
+    }
+
+    fn load_shader_file<F: Into<ShaderFile>>(&mut self, file: F) -> Option<Rc<Shader>> {
+        load_shader_file(self.skeleton, &self.defines, file.into())
+            .map_err(|e| self.shader_error = Some(e))
+            .ok()
+    }
+
+    // reuses an already compiled module, for many pipelines out of one wgsl file
+    pub fn with_shared_shader(mut self, shader: Rc<Shader>) -> Self {
+        self.vertex_shader = Some(shader.clone());
+        self.fragment_shader = Some(shader);
+        self
+    }
+
+    pub fn with_entry_points(mut self, vertex: &str, fragment: &str) -> Self {
+        self.vertex_entry_point = vertex.to_string();
+        self.fragment_entry_point = fragment.to_string();
+        self
+    }
+
+    pub fn with_vertex_entry_point(mut self, vertex: &str) -> Self {
+        self.vertex_entry_point = vertex.to_string();
+        self
+    }
+
+    pub fn with_fragment_entry_point(mut self, fragment: &str) -> Self {
+        self.fragment_entry_point = fragment.to_string();
+        self
+    }
+
+    // replaces every layout with this one
     pub fn with_vertex_buffer_layout(mut self, vertex_buffer_layout: wgpu::VertexBufferLayout<'static>) -> Self {
-        self.vertex_buffer_layout = Some(vertex_buffer_layout);
+        self.vertex_buffer_layouts = vec![vertex_buffer_layout.into()];
+        self
+    }
+
+    pub fn with_vertex_buffer_layouts(mut self, vertex_buffer_layouts: &[wgpu::VertexBufferLayout<'static>]) -> Self {
+        self.vertex_buffer_layouts = vertex_buffer_layouts.iter().cloned().map(Into::into).collect();
+        self
+    }
+
+    // adds a layout in the next slot
+    pub fn add_vertex_buffer_layout(mut self, vertex_buffer_layout: wgpu::VertexBufferLayout<'static>) -> Self {
+        self.vertex_buffer_layouts.push(vertex_buffer_layout.into());
+        self
+    }
+
+    // adds a per instance layout in the next slot, see data::Instance
+    pub fn add_instance_buffer_layout(self, instance_buffer_layout: wgpu::VertexBufferLayout<'static>) -> Self {
+        self.add_vertex_buffer_layout(wgpu::VertexBufferLayout {
+            step_mode: wgpu::VertexStepMode::Instance,
+            ..instance_buffer_layout
+        })
+    }
+
+    // for fullscreen triangles and vertex pulling, the shader makes up its own vertices
+    pub fn without_vertex_buffers(mut self) -> Self {
+        self.vertex_buffer_layouts.clear();
         self
     }
 
@@ -74,94 +538,1042 @@ impl<'a, S> PipelineBuilder<'a, S> {
         self
     }
 
-    pub fn layout_for_camera3d(mut self) -> Self {
-        let camera = self.app.camera3d.as_ref().expect("cannot layout camera3d: no camera3d in use!");
-        let device = &self.app.skeleton.device;
-        assert!(self.pipeline_layout.is_none(), "cannot use camera3d, pipeline layout is already defined! (hint: did you already call a layout_for_*() function?)");
-        self.pipeline_layout = Some(device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some(format!("{} render pipeline layout", self.name).as_str()),
-            bind_group_layouts: &[
-                &camera.bind_group_layout,
-            ],
-            push_constant_ranges: &[],
-        }));
+    pub fn with_depth(mut self, format: wgpu::TextureFormat, compare: wgpu::CompareFunction, write_enabled: bool) -> Self {
+        self.depth_stencil = Some(wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: write_enabled,
+            depth_compare: compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        });
         self
     }
 
-    pub fn layout_for_text2d(mut self) -> Self {
-        let text = self.app.text2d.as_ref().expect("cannot layout text2d: no text2d in use!");
-        let device = &self.app.skeleton.device;
-        assert!(self.pipeline_layout.is_none(), "cannot use camera3d, pipeline layout is already defined! (hint: did you already call a layout_for_*() function?)");
-        self.pipeline_layout = Some(device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+    // depth against the skeleton's own depth texture, the usual choice for 3d
+    pub fn with_skeleton_depth(self, compare: wgpu::CompareFunction, write_enabled: bool) -> Self {
+        let format = self.skeleton.depth_format()
+            .expect("cannot use skeleton depth: skeleton has no depth texture! (hint: SkeletonBuilder::with_depth)");
+        self.with_depth(format, compare, write_enabled)
+    }
+
+    // constant is in depth buffer units, slope_scale scales with the polygon's depth slope.
+    // needs with_depth
+    pub fn with_depth_bias(mut self, constant: i32, slope_scale: f32, clamp: f32) -> Self {
+        self.depth_bias = wgpu::DepthBiasState {
+            constant,
+            slope_scale,
+            clamp,
+        };
+        self
+    }
+
+    // same ops for both faces, see with_stencil_faces for two sided stencil
+    pub fn with_stencil(self, face: wgpu::StencilFaceState, read_mask: u32, write_mask: u32) -> Self {
+        self.with_stencil_faces(face, face, read_mask, write_mask)
+    }
+
+    // needs a depth format with stencil (Depth24PlusStencil8 etc.), the reference value is set
+    // on the render pass with set_stencil_reference
+    pub fn with_stencil_faces(
+        mut self,
+        front: wgpu::StencilFaceState,
+        back: wgpu::StencilFaceState,
+        read_mask: u32,
+        write_mask: u32,
+    ) -> Self {
+        self.stencil = wgpu::StencilState {
+            front,
+            back,
+            read_mask,
+            write_mask,
+        };
+        self
+    }
+
+    // for strip topologies, the index value that restarts the strip (0xFFFF or 0xFFFFFFFF)
+    pub fn with_strip_index_format(mut self, strip_index_format: Option<wgpu::IndexFormat>) -> Self {
+        self.strip_index_format = strip_index_format;
+        self
+    }
+
+    pub fn with_unclipped_depth(mut self, unclipped_depth: bool) -> Self {
+        self.unclipped_depth = unclipped_depth;
+        self
+    }
+
+    pub fn with_conservative_rasterization(mut self, conservative: bool) -> Self {
+        self.conservative = conservative;
+        self
+    }
+
+    // skips the fragment stage, needs with_depth
+    pub fn depth_only(mut self) -> Self {
+        self.depth_only = true;
+        self
+    }
+
+    // sets the blend of every color target, see with_target_blend for a single one
+    pub fn with_blend(mut self, blend: BlendPreset) -> Self {
+        for target in &mut self.color_targets {
+            target.blend = blend;
+        }
+        self
+    }
+
+    pub fn with_write_mask(mut self, write_mask: wgpu::ColorWrites) -> Self {
+        for target in &mut self.color_targets {
+            target.write_mask = write_mask;
+        }
+        self
+    }
+
+    pub fn with_target_blend(mut self, index: usize, blend: BlendPreset) -> Self {
+        self.color_target_mut(index).blend = blend;
+        self
+    }
+
+    pub fn with_target_write_mask(mut self, index: usize, write_mask: wgpu::ColorWrites) -> Self {
+        self.color_target_mut(index).write_mask = write_mask;
+        self
+    }
+
+    // replaces the default single surface target, for g-buffers and other mrt passes
+    pub fn with_color_targets(mut self, color_targets: &[ColorTarget]) -> Self {
+        self.color_targets = color_targets.to_vec();
+        self
+    }
+
+    fn color_target_mut(&mut self, index: usize) -> &mut ColorTarget {
+        let name = &self.name;
+        self.color_targets.get_mut(index)
+            .unwrap_or_else(|| panic!("cannot set color target {} of {} pipeline: there is no such target!", index, name))
+    }
+
+    // has to match the sample count of whatever the pipeline draws into. without this it's the
+    // skeleton's for pipelines drawing into the skeleton's target and 1 for offscreen ones (any
+    // color target with its own format, or depth only into a depth format the skeleton
+    // doesn't use)
+    pub fn with_sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = Some(sample_count);
+        self
+    }
+
+    // what wgpu checks the pipeline against, the adapter's own features only count when the
+    // device was created with TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+    fn format_features(&self, format: wgpu::TextureFormat) -> wgpu::TextureFormatFeatures {
+        let device_features = self.skeleton.device.features();
+        if device_features.contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+            self.skeleton.adapter.get_texture_format_features(format)
+        } else {
+            format.guaranteed_format_features(device_features)
+        }
+    }
+
+    fn target_sample_count(&self) -> u32 {
+        let offscreen = if self.depth_only {
+            self.depth_stencil.as_ref().map(|depth_stencil| depth_stencil.format) != self.skeleton.depth_format()
+        } else {
+            self.color_targets.iter().any(|target| target.format.is_some())
+        };
+        match self.sample_count {
+            Some(sample_count) => sample_count,
+            None if offscreen => 1,
+            None => self.skeleton.sample_count(),
+        }
+    }
+
+    pub fn with_alpha_to_coverage(mut self, alpha_to_coverage: bool) -> Self {
+        self.alpha_to_coverage = alpha_to_coverage;
+        self
+    }
+
+    pub fn layout_for_camera3d(self) -> Self {
+        self.layout_for_slots(&[BindSlot::Camera3D])
+    }
+
+    pub fn layout_for_text2d(self) -> Self {
+        self.layout_for_slots(&[BindSlot::Text2D, BindSlot::GlyphAtlas])
+    }
+
+    // builds the layout out of the app's own resources, slot n is bind group n
+    pub fn layout_for_slots(mut self, slots: &[BindSlot]) -> Self {
+        assert!(self.pipeline_layout.is_none(), "cannot layout {} pipeline, pipeline layout is already defined! (hint: did you already call a layout_for_*() function?)", self.name);
+        let layouts: Vec<_> = slots.iter()
+            .map(|slot| self.layouts.bind_group_layout(slot)
+                .unwrap_or_else(|| panic!("cannot layout {} pipeline: no {:?} in use!", self.name, slot)))
+            .collect();
+        self.pipeline_layout = Some(self.skeleton.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some(format!("{} render pipeline layout", self.name).as_str()),
-            bind_group_layouts: &[
-                &text.bind_group_layout,
-                &text.glyph_atlas.glyph_bind_group_layout,
-            ],
+            bind_group_layouts: &layouts,
             push_constant_ranges: &[],
         }));
+        self.bind_slots = slots.to_vec();
         self
     }
 
+    // the render code has to bind these itself, see layout_for_slots to have App do it
     pub fn use_custom_bind_group_layouts(mut self, layouts: &[&wgpu::BindGroupLayout]) -> Self {
-        let device = &self.app.skeleton.device;
+        let device = &self.skeleton.device;
         self.pipeline_layout = Some(device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some(format!("{} render pipeline layout", self.name).as_str()),
             bind_group_layouts: layouts,
             push_constant_ranges: &[],
         }));
+        self.bind_slots = vec![BindSlot::Manual; layouts.len()];
         self
     }
 
-    pub fn build(self) -> (AppBuilder<'a, S>, NamedPipeline<'a>) {
-        let device = &self.app.skeleton.device;
-        let config = &self.app.skeleton.config;
-        let shader = &self.shader.expect(format!("cannot build {} pipeline without shader!", self.name).as_str());
-        let vertex_buffer_layout = self.vertex_buffer_layout.expect(format!("cannot build {} pipeline without vertex buffer layout!", self.name).as_str());
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some(format!("{} render pipeline", self.name).as_str()),
-            layout: self.pipeline_layout.as_ref(),
-            vertex: wgpu::VertexState {
-                module: shader,
-                entry_point: "vs_main",
-                buffers: &[
-                    vertex_buffer_layout
-                ],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
+    // derives the pipeline layout from the shaders' @group/@binding declarations, so call it
+    // after the shader and entry point methods. group n uses slots[n]: the app's layout for
+    // built-in slots (checked against what the shader declares) and a layout made from the
+    // shader for Manual and any groups past the end of slots. bind groups for those can be
+    // made with the built pipeline's get_bind_group_layout
+    pub fn layout_from_reflection(mut self, slots: &[BindSlot]) -> Self {
+        assert!(self.pipeline_layout.is_none(), "cannot layout {} pipeline, pipeline layout is already defined! (hint: did you already call a layout_for_*() function?)", self.name);
+        if let Err(e) = self.reflect_layout(slots) {
+            self.reflection_error = Some(e);
+        }
+        self
+    }
+
+    fn reflect_layout(&mut self, slots: &[BindSlot]) -> Result<(), PipelineError> {
+        let mut stages = vec![(&self.vertex_shader, self.vertex_entry_point.as_str(), naga::ShaderStage::Vertex)];
+        if !self.depth_only {
+            stages.push((&self.fragment_shader, self.fragment_entry_point.as_str(), naga::ShaderStage::Fragment));
+        }
+        let (pipeline_layout, bind_slots) = reflected_pipeline_layout(
+            self.skeleton,
+            self.layouts,
+            &self.name,
+            &format!("{} render pipeline layout", self.name),
+            &stages,
+            slots,
+            &[],
+        )?;
+        self.pipeline_layout = Some(pipeline_layout);
+        self.bind_slots = bind_slots;
+        Ok(())
+    }
+
+    // replaces the vertex buffer layouts with a single per vertex buffer holding every @location
+    // input of the vertex entry point, tightly packed in location order. call it after the
+    // shader and entry point methods
+    pub fn with_reflected_vertex_layout(mut self) -> Self {
+        let name = self.name.clone();
+        let Some(module) = self.vertex_shader.as_ref().and_then(|shader| shader.reflection.as_ref()) else {
+            if self.vertex_shader.is_some() {
+                self.reflection_error = Some(PipelineError::Reflection {
+                    pipeline: name,
+                    message: "the vertex shader was not reflected (only wgsl is)".to_string(),
+                });
+            }
+            return self;
+        };
+        match reflect::vertex_buffer_layout(&reflect::vertex_inputs(module, &self.vertex_entry_point)) {
+            Ok(layout) => self.vertex_buffer_layouts = vec![layout],
+            Err(message) => self.reflection_error = Some(PipelineError::Reflection { pipeline: name, message }),
+        }
+        self
+    }
+
+    // checks everything build() would otherwise panic on, without consuming the builder
+    pub fn validate(&self) -> Result<(), PipelineError> {
+        if let Some(e) = &self.shader_error {
+            return Err(PipelineError::Shader(e.clone()));
+        }
+        if let Some(define) = &self.late_define {
+            return Err(PipelineError::DefineAfterShader {
+                pipeline: self.name.clone(),
+                define: define.clone(),
+            });
+        }
+        if let Some(e) = &self.reflection_error {
+            return Err(e.clone());
+        }
+        let name = self.name.to_string();
+        let vertex_shader = self.vertex_shader.as_ref().ok_or_else(|| PipelineError::MissingShader(name.clone()))?;
+        let mut locations = std::collections::HashSet::new();
+        for attribute in self.vertex_buffer_layouts.iter().flat_map(|layout| &layout.attributes) {
+            if !locations.insert(attribute.shader_location) {
+                return Err(PipelineError::ShaderLocationConflict {
+                    pipeline: name,
+                    location: attribute.shader_location,
+                });
+            }
+        }
+        check_entry_point(&name, vertex_shader, &self.vertex_entry_point, naga::ShaderStage::Vertex)?;
+        if let Some(module) = &vertex_shader.reflection {
+            let inputs = reflect::vertex_inputs(module, &self.vertex_entry_point);
+            reflect::check_vertex_buffers(&inputs, &self.vertex_buffer_layouts)
+                .map_err(|(location, message)| PipelineError::VertexInputMismatch {
+                    pipeline: name.clone(),
+                    location,
+                    message,
+                })?;
+        }
+
+        if self.depth_bias.is_enabled() && self.depth_stencil.is_none() {
+            return Err(PipelineError::DepthBiasWithoutDepth(name));
+        }
+
+        if self.stencil.is_enabled() {
+            let has_stencil = self.depth_stencil.as_ref().is_some_and(|depth_stencil| depth_stencil.format.has_stencil_aspect());
+            if !has_stencil {
+                return Err(PipelineError::MissingStencilAspect(name));
+            }
+        }
+
+        if self.strip_index_format.is_some() && !self.primitive_topology.is_strip() {
+            return Err(PipelineError::StripIndexFormatWithoutStrip(name));
+        }
+
+        let mut required_features = wgpu::Features::empty();
+        if self.unclipped_depth {
+            required_features |= wgpu::Features::DEPTH_CLIP_CONTROL;
+        }
+        if self.conservative {
+            required_features |= wgpu::Features::CONSERVATIVE_RASTERIZATION;
+        }
+        if self.polygon_mode == wgpu::PolygonMode::Line {
+            required_features |= wgpu::Features::POLYGON_MODE_LINE;
+        }
+        if self.polygon_mode == wgpu::PolygonMode::Point {
+            required_features |= wgpu::Features::POLYGON_MODE_POINT;
+        }
+        let missing_features = required_features - self.skeleton.device.features();
+        if !missing_features.is_empty() {
+            return Err(PipelineError::MissingFeatures {
+                pipeline: name,
+                features: missing_features,
+            });
+        }
+
+        if self.depth_only {
+            if self.depth_stencil.is_none() {
+                return Err(PipelineError::DepthOnlyWithoutDepth(name));
+            }
+        } else {
+            let fragment_shader = self.fragment_shader.as_ref().ok_or_else(|| PipelineError::MissingShader(name.clone()))?;
+            check_entry_point(&name, fragment_shader, &self.fragment_entry_point, naga::ShaderStage::Fragment)?;
+
+            for (index, target) in self.color_targets.iter().enumerate() {
+                let format = target.format.unwrap_or(self.skeleton.config.format);
+                let blendable = self.format_features(format).flags.contains(wgpu::TextureFormatFeatureFlags::BLENDABLE);
+                if target.blend != BlendPreset::Replace && !blendable {
+                    return Err(PipelineError::FormatNotBlendable {
+                        pipeline: name,
+                        target: index,
+                        format,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> NamedPipeline {
+        self.try_build().unwrap_or_else(|e| panic!("{}!", e))
+    }
+
+    pub fn try_build(self) -> Result<NamedPipeline, PipelineError> {
+        self.validate()?;
+        let config = &self.skeleton.config;
+        let sample_count = self.target_sample_count();
+        let mut recipe = PipelineRecipe {
+            name: self.name.clone(),
+            vertex_shader: self.vertex_shader.clone().unwrap(),
+            fragment_shader: if self.depth_only { None } else { self.fragment_shader.clone() },
+            vertex_entry_point: self.vertex_entry_point.clone(),
+            fragment_entry_point: self.fragment_entry_point.clone(),
+            pipeline_layout: self.pipeline_layout.map(Rc::new),
+            vertex_buffer_layouts: self.vertex_buffer_layouts.clone(),
             primitive: wgpu::PrimitiveState {
                 topology: self.primitive_topology,
-                strip_index_format: None,
+                strip_index_format: self.strip_index_format,
                 front_face: self.front_face_format,
                 cull_mode: self.cull_mode,
                 polygon_mode: self.polygon_mode,
-                // needs Features::DEPTH_CLIP_CONTROL
-                unclipped_depth: false,
-                // needs Features::CONSERVATIVE_RASTERIZATION
-                conservative: false,
+                unclipped_depth: self.unclipped_depth,
+                conservative: self.conservative,
             },
-            depth_stencil: None,
+            depth_stencil: self.depth_stencil.map(|depth_stencil| wgpu::DepthStencilState {
+                bias: self.depth_bias,
+                stencil: self.stencil.clone(),
+                ..depth_stencil
+            }),
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
-                alpha_to_coverage_enabled: false,
+                alpha_to_coverage_enabled: self.alpha_to_coverage,
+            },
+            color_targets: self.color_targets.iter()
+                .map(|target| Some(target.state(config.format)))
+                .collect(),
+            watch: ShaderWatch::default(),
+        };
+        recipe.watch = ShaderWatch::new(recipe.shaders().map(Rc::as_ref));
+        Ok(NamedPipeline {
+            name: self.name,
+            pipeline: recipe.create(&self.skeleton.device),
+            bind_slots: self.bind_slots,
+            recipe,
+        })
+    }
+}
+
+// everything a pipeline was built from, so it can be rebuilt with new shaders
+#[derive(Clone)]
+pub struct PipelineRecipe {
+    pub name: String,
+    pub vertex_shader: Rc<Shader>,
+    // None for depth only pipelines
+    pub fragment_shader: Option<Rc<Shader>>,
+    pub vertex_entry_point: String,
+    pub fragment_entry_point: String,
+    pub pipeline_layout: Option<Rc<wgpu::PipelineLayout>>,
+    pub vertex_buffer_layouts: Vec<OwnedVertexBufferLayout>,
+    pub primitive: wgpu::PrimitiveState,
+    pub depth_stencil: Option<wgpu::DepthStencilState>,
+    pub multisample: wgpu::MultisampleState,
+    pub color_targets: Vec<Option<wgpu::ColorTargetState>>,
+    // what the shader files looked like when this recipe last checked
+    pub watch: ShaderWatch,
+}
+
+impl PipelineRecipe {
+    pub fn create(&self, device: &wgpu::Device) -> wgpu::RenderPipeline {
+        let vertex_buffer_layouts: Vec<_> = self.vertex_buffer_layouts.iter()
+            .map(OwnedVertexBufferLayout::as_layout)
+            .collect();
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(format!("{} render pipeline", self.name).as_str()),
+            layout: self.pipeline_layout.as_deref(),
+            vertex: wgpu::VertexState {
+                module: &self.vertex_shader.module,
+                entry_point: &self.vertex_entry_point,
+                buffers: &vertex_buffer_layouts,
             },
+            fragment: self.fragment_shader.as_ref().map(|fragment_shader| wgpu::FragmentState {
+                module: &fragment_shader.module,
+                entry_point: &self.fragment_entry_point,
+                targets: &self.color_targets,
+            }),
+            primitive: self.primitive,
+            depth_stencil: self.depth_stencil.clone(),
+            multisample: self.multisample,
             multiview: None,
-        });
-        (
-            self.app,
-            NamedPipeline {
-                name: self.name,
-                pipeline: render_pipeline,
+        })
+    }
+
+    pub fn shaders(&self) -> impl Iterator<Item = &Rc<Shader>> {
+        std::iter::once(&self.vertex_shader).chain(self.fragment_shader.as_ref())
+    }
+
+    // None if none of the shader files changed. on errors the old pipeline should stay in use
+    pub fn rebuild_if_changed(&self, device: &wgpu::Device) -> Option<Result<(PipelineRecipe, wgpu::RenderPipeline), PipelineError>> {
+        if !self.watch.changed_on_disk() {
+            return None;
+        }
+        // a broken file is only reported once, not on every poll
+        self.watch.mark_seen();
+        Some(self.rebuild(device))
+    }
+
+    fn rebuild(&self, device: &wgpu::Device) -> Result<(PipelineRecipe, wgpu::RenderPipeline), PipelineError> {
+        let reload = |shader: &Rc<Shader>| -> Result<Rc<Shader>, PipelineError> {
+            match shader.reload(device) {
+                Some(reloaded) => Ok(Rc::new(reloaded?)),
+                None => Ok(shader.clone()),
+            }
+        };
+        let vertex_shader = reload(&self.vertex_shader)?;
+        // one file for both stages only gets compiled once
+        let fragment_shader = match &self.fragment_shader {
+            Some(shader) if Rc::ptr_eq(shader, &self.vertex_shader) => Some(vertex_shader.clone()),
+            Some(shader) => Some(reload(shader)?),
+            None => None,
+        };
+
+        check_entry_point(&self.name, &vertex_shader, &self.vertex_entry_point, naga::ShaderStage::Vertex)?;
+        if let Some(fragment_shader) = &fragment_shader {
+            check_entry_point(&self.name, fragment_shader, &self.fragment_entry_point, naga::ShaderStage::Fragment)?;
+        }
+
+        let mut recipe = PipelineRecipe {
+            vertex_shader,
+            fragment_shader,
+            ..self.clone()
+        };
+        recipe.watch = ShaderWatch::new(recipe.shaders().map(Rc::as_ref));
+        // the shaders can still disagree with the layout or each other, catch that instead of
+        // letting wgpu's default error handler panic
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipeline = recipe.create(device);
+        if let Some(Some(e)) = poll_ready(device.pop_error_scope()) {
+            return Err(PipelineError::Validation {
+                pipeline: self.name.clone(),
+                message: e.to_string(),
+            });
+        }
+        Ok((recipe, pipeline))
+    }
+}
+
+// ====== compute pipelines ======
+
+pub struct NamedComputePipeline {
+    pub name: String,
+    pub pipeline: wgpu::ComputePipeline,
+    // bind group index -> what to bind there
+    pub bind_slots: Vec<BindSlot>,
+    // the entry point's @workgroup_size, dispatch sizes are worked out from it
+    pub workgroup_size: [u32; 3],
+    // the device's max_compute_workgroups_per_dimension, dispatch checks against it
+    pub max_workgroups: u32,
+    pub recipe: ComputePipelineRecipe,
+}
+
+impl NamedComputePipeline {
+    // see also AppBuilder::compute_pipeline_builder and App::compute_pipeline_builder
+    pub fn named_for<'r>(name: &str, skeleton: &'r AppSkeleton, layouts: &'r dyn BindGroupLayouts) -> ComputePipelineBuilder<'r> {
+        ComputePipelineBuilder {
+            skeleton,
+            layouts,
+            name: name.to_string(),
+            shader: None,
+            shader_error: None,
+            defines: Defines::new(),
+            late_define: None,
+            reflection_error: None,
+            entry_point: "cs_main".to_string(),
+            pipeline_layout: None,
+            bind_slots: Vec::new(),
+            push_constant_ranges: Vec::new(),
+            workgroup_size: None,
+        }
+    }
+
+    // enough workgroups to cover elements on each axis, the shader has to skip the extra
+    // invocations of the last workgroup
+    pub fn workgroups_for(&self, elements: [u32; 3]) -> [u32; 3] {
+        workgroup_counts(elements, self.workgroup_size)
+    }
+
+    // workgroups_for, or an error if an axis needs more workgroups than the device allows (65535
+    // by default). spread big 1d dispatches over y and rebuild the index in the shader
+    pub fn checked_workgroups_for(&self, elements: [u32; 3]) -> Result<[u32; 3], BindError> {
+        let workgroups = self.workgroups_for(elements);
+        if workgroups.iter().any(|count| *count > self.max_workgroups) {
+            return Err(BindError::TooManyWorkgroups {
+                pipeline: self.name.clone(),
+                workgroups,
+                max: self.max_workgroups,
+            });
+        }
+        Ok(workgroups)
+    }
+
+    // only dispatches, the pipeline and bind groups have to be set already (see
+    // App::bind_compute_pipeline)
+    pub fn dispatch(&self, compute_pass: &mut wgpu::ComputePass, elements: [u32; 3]) -> Result<(), BindError> {
+        let [x, y, z] = self.checked_workgroups_for(elements)?;
+        compute_pass.dispatch_workgroups(x, y, z);
+        Ok(())
+    }
+}
+
+impl RegistryPipeline for NamedComputePipeline {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn reload_if_changed(&mut self, device: &wgpu::Device) -> Option<Result<(), PipelineError>> {
+        self.recipe.rebuild_if_changed(device).map(|rebuilt| rebuilt.map(|(recipe, pipeline)| {
+            // the new shader might have changed @workgroup_size
+            if let Some(workgroup_size) = recipe.workgroup_size() {
+                self.workgroup_size = workgroup_size;
+            }
+            self.recipe = recipe;
+            self.pipeline = pipeline;
+        }))
+    }
+}
+
+// workgroups needed for elements invocations, rounded up
+pub fn workgroup_count(elements: u32, workgroup_size: u32) -> u32 {
+    elements.div_ceil(workgroup_size.max(1))
+}
+
+// workgroup_count on every axis
+pub fn workgroup_counts(elements: [u32; 3], workgroup_size: [u32; 3]) -> [u32; 3] {
+    [
+        workgroup_count(elements[0], workgroup_size[0]),
+        workgroup_count(elements[1], workgroup_size[1]),
+        workgroup_count(elements[2], workgroup_size[2]),
+    ]
+}
+
+// mirrors PipelineBuilder, minus everything that only matters for drawing
+pub struct ComputePipelineBuilder<'r> {
+    pub skeleton: &'r AppSkeleton,
+    pub layouts: &'r dyn BindGroupLayouts,
+    pub name: String,
+    pub shader: Option<Rc<Shader>>,
+    // from the shader methods, reported by validate
+    pub shader_error: Option<ShaderError>,
+    // for the preprocessor, only seen by shaders added after them
+    pub defines: Defines,
+    // the first define that came after a shader was already compiled, reported by validate
+    pub late_define: Option<String>,
+    // from layout_from_reflection, reported by validate
+    pub reflection_error: Option<PipelineError>,
+    pub entry_point: String,
+    // None lets wgpu derive the layout, get it back with the pipeline's get_bind_group_layout
+    pub pipeline_layout: Option<wgpu::PipelineLayout>,
+    pub bind_slots: Vec<BindSlot>,
+    // needs Features::PUSH_CONSTANTS
+    pub push_constant_ranges: Vec<wgpu::PushConstantRange>,
+    // None means the entry point's @workgroup_size
+    pub workgroup_size: Option<[u32; 3]>,
+}
+
+impl ComputePipelineBuilder<'_> {
+    // same as #define NAME value at the top of the shader, use "" for a plain #ifdef flag.
+    // has to come before the shader methods, validate reports the ones that don't
+    pub fn with_define(mut self, name: &str, value: &str) -> Self {
+        if self.shader.is_some() || self.shader_error.is_some() {
+            self.late_define.get_or_insert_with(|| name.to_string());
+        }
+        self.defines.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    // wgsl goes through the preprocessor first
+    pub fn with_shader(mut self, source: wgpu::ShaderSource) -> Self {
+        match compile_shader(self.skeleton, &self.defines, &format!("{} compute shader", self.name), source) {
+            Ok(shader) => self.shader = Some(shader),
+            Err(e) => self.shader_error = Some(e),
+        }
+        self
+    }
+
+    // wgsl from disk, PipelineRegistry::reload_changed rebuilds the pipeline when it (or anything
+    // it includes) changes. takes a path or a ShaderFile with its own defines
+    pub fn with_shader_file<F: Into<ShaderFile>>(mut self, file: F) -> Self {
+        match load_shader_file(self.skeleton, &self.defines, file.into()) {
+            Ok(shader) => self.shader = Some(shader),
+            Err(e) => self.shader_error = Some(e),
+        }
+        self
+    }
+
+    // reuses an already compiled module, for many passes out of one wgsl file
+    pub fn with_shared_shader(mut self, shader: Rc<Shader>) -> Self {
+        self.shader = Some(shader);
+        self
+    }
+
+    pub fn with_entry_point(mut self, entry_point: &str) -> Self {
+        self.entry_point = entry_point.to_string();
+        self
+    }
+
+    // only needed for shaders naga can't reflect
+    pub fn with_workgroup_size(mut self, workgroup_size: [u32; 3]) -> Self {
+        self.workgroup_size = Some(workgroup_size);
+        self
+    }
+
+    // size bytes of push constants at offset 0 (a multiple of 4), has to come before the layout
+    // methods
+    pub fn with_push_constants(mut self, size: u32) -> Self {
+        assert!(self.pipeline_layout.is_none(), "cannot add push constants to {} compute pipeline, pipeline layout is already defined!", self.name);
+        self.push_constant_ranges = vec![wgpu::PushConstantRange {
+            stages: wgpu::ShaderStages::COMPUTE,
+            range: 0..size,
+        }];
+        self
+    }
+
+    // builds the layout out of the app's own resources, slot n is bind group n
+    pub fn layout_for_slots(mut self, slots: &[BindSlot]) -> Self {
+        assert!(self.pipeline_layout.is_none(), "cannot layout {} compute pipeline, pipeline layout is already defined! (hint: did you already call a layout_for_*() function?)", self.name);
+        let layouts: Vec<_> = slots.iter()
+            .map(|slot| self.layouts.bind_group_layout(slot)
+                .unwrap_or_else(|| panic!("cannot layout {} compute pipeline: no {:?} in use!", self.name, slot)))
+            .collect();
+        self.pipeline_layout = Some(self.skeleton.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(format!("{} compute pipeline layout", self.name).as_str()),
+            bind_group_layouts: &layouts,
+            push_constant_ranges: &self.push_constant_ranges,
+        }));
+        self.bind_slots = slots.to_vec();
+        self
+    }
+
+    // the compute code has to bind these itself, see layout_for_slots to have App do it
+    pub fn use_custom_bind_group_layouts(mut self, layouts: &[&wgpu::BindGroupLayout]) -> Self {
+        let device = &self.skeleton.device;
+        self.pipeline_layout = Some(device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(format!("{} compute pipeline layout", self.name).as_str()),
+            bind_group_layouts: layouts,
+            push_constant_ranges: &self.push_constant_ranges,
+        }));
+        self.bind_slots = vec![BindSlot::Manual; layouts.len()];
+        self
+    }
+
+    // see PipelineBuilder::layout_from_reflection
+    pub fn layout_from_reflection(mut self, slots: &[BindSlot]) -> Self {
+        assert!(self.pipeline_layout.is_none(), "cannot layout {} compute pipeline, pipeline layout is already defined! (hint: did you already call a layout_for_*() function?)", self.name);
+        let layout = reflected_pipeline_layout(
+            self.skeleton,
+            self.layouts,
+            &self.name,
+            &format!("{} compute pipeline layout", self.name),
+            &[(&self.shader, self.entry_point.as_str(), naga::ShaderStage::Compute)],
+            slots,
+            &self.push_constant_ranges,
+        );
+        match layout {
+            Ok((pipeline_layout, bind_slots)) => {
+                self.pipeline_layout = Some(pipeline_layout);
+                self.bind_slots = bind_slots;
+            },
+            Err(e) => self.reflection_error = Some(e),
+        }
+        self
+    }
+
+    // checks everything build() would otherwise panic on, without consuming the builder
+    pub fn validate(&self) -> Result<(), PipelineError> {
+        if let Some(e) = &self.shader_error {
+            return Err(PipelineError::Shader(e.clone()));
+        }
+        if let Some(define) = &self.late_define {
+            return Err(PipelineError::DefineAfterShader {
+                pipeline: self.name.clone(),
+                define: define.clone(),
+            });
+        }
+        if let Some(e) = &self.reflection_error {
+            return Err(e.clone());
+        }
+        let name = self.name.to_string();
+        let shader = self.shader.as_ref().ok_or_else(|| PipelineError::MissingShader(name.clone()))?;
+        check_entry_point(&name, shader, &self.entry_point, naga::ShaderStage::Compute)?;
+        if self.workgroup_size.is_none() && reflected_workgroup_size(shader, &self.entry_point).is_none() {
+            return Err(PipelineError::Reflection {
+                pipeline: name,
+                message: "cannot tell the workgroup size, use with_workgroup_size".to_string(),
+            });
+        }
+
+        for range in &self.push_constant_ranges {
+            let aligned = |offset: u32| offset % wgpu::PUSH_CONSTANT_ALIGNMENT == 0;
+            if range.range.is_empty() || !aligned(range.range.start) || !aligned(range.range.end) {
+                return Err(PipelineError::Validation {
+                    pipeline: name,
+                    message: format!("push constants {:?} have to be a non empty multiple of {} bytes", range.range, wgpu::PUSH_CONSTANT_ALIGNMENT),
+                });
+            }
+        }
+        if !self.push_constant_ranges.is_empty() {
+            let missing_features = wgpu::Features::PUSH_CONSTANTS - self.skeleton.device.features();
+            if !missing_features.is_empty() {
+                return Err(PipelineError::MissingFeatures {
+                    pipeline: name,
+                    features: missing_features,
+                });
             }
-        )
+            let size = self.push_constant_ranges.iter().map(|range| range.range.end).max().unwrap_or(0);
+            let max_size = self.skeleton.device.limits().max_push_constant_size;
+            if size > max_size {
+                return Err(PipelineError::Validation {
+                    pipeline: name,
+                    message: format!("{} bytes of push constants, the device allows {}", size, max_size),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> NamedComputePipeline {
+        self.try_build().unwrap_or_else(|e| panic!("{}!", e))
+    }
+
+    pub fn try_build(self) -> Result<NamedComputePipeline, PipelineError> {
+        self.validate()?;
+        // push constants need a layout to live in, even without bind groups
+        let pipeline_layout = match self.pipeline_layout {
+            None if !self.push_constant_ranges.is_empty() => Some(self.skeleton.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(format!("{} compute pipeline layout", self.name).as_str()),
+                bind_group_layouts: &[],
+                push_constant_ranges: &self.push_constant_ranges,
+            })),
+            pipeline_layout => pipeline_layout,
+        };
+        let shader = self.shader.unwrap();
+        let recipe = ComputePipelineRecipe {
+            name: self.name.clone(),
+            watch: ShaderWatch::new([shader.as_ref()]),
+            shader,
+            entry_point: self.entry_point,
+            pipeline_layout: pipeline_layout.map(Rc::new),
+            fixed_workgroup_size: self.workgroup_size,
+        };
+        Ok(NamedComputePipeline {
+            name: self.name,
+            pipeline: recipe.create(&self.skeleton.device),
+            bind_slots: self.bind_slots,
+            workgroup_size: recipe.workgroup_size().unwrap(),
+            max_workgroups: self.skeleton.device.limits().max_compute_workgroups_per_dimension,
+            recipe,
+        })
+    }
+}
+
+// everything a compute pipeline was built from, so it can be rebuilt with a new shader
+#[derive(Clone)]
+pub struct ComputePipelineRecipe {
+    pub name: String,
+    pub shader: Rc<Shader>,
+    pub entry_point: String,
+    pub pipeline_layout: Option<Rc<wgpu::PipelineLayout>>,
+    // from with_workgroup_size, wins over the shader's
+    pub fixed_workgroup_size: Option<[u32; 3]>,
+    // what the shader file looked like when this recipe last checked
+    pub watch: ShaderWatch,
+}
+
+impl ComputePipelineRecipe {
+    pub fn create(&self, device: &wgpu::Device) -> wgpu::ComputePipeline {
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(format!("{} compute pipeline", self.name).as_str()),
+            layout: self.pipeline_layout.as_deref(),
+            module: &self.shader.module,
+            entry_point: &self.entry_point,
+        })
+    }
+
+    pub fn workgroup_size(&self) -> Option<[u32; 3]> {
+        self.fixed_workgroup_size.or_else(|| reflected_workgroup_size(&self.shader, &self.entry_point))
+    }
+
+    // None if the shader file didn't change. on errors the old pipeline should stay in use
+    pub fn rebuild_if_changed(&self, device: &wgpu::Device) -> Option<Result<(ComputePipelineRecipe, wgpu::ComputePipeline), PipelineError>> {
+        if !self.watch.changed_on_disk() {
+            return None;
+        }
+        // a broken file is only reported once, not on every poll
+        self.watch.mark_seen();
+        Some(self.rebuild(device))
     }
-}
\ No newline at end of file
+
+    fn rebuild(&self, device: &wgpu::Device) -> Result<(ComputePipelineRecipe, wgpu::ComputePipeline), PipelineError> {
+        let shader = match self.shader.reload(device) {
+            Some(reloaded) => Rc::new(reloaded?),
+            None => self.shader.clone(),
+        };
+        check_entry_point(&self.name, &shader, &self.entry_point, naga::ShaderStage::Compute)?;
+
+        let recipe = ComputePipelineRecipe {
+            watch: ShaderWatch::new([shader.as_ref()]),
+            shader,
+            ..self.clone()
+        };
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipeline = recipe.create(device);
+        if let Some(Some(e)) = poll_ready(device.pop_error_scope()) {
+            return Err(PipelineError::Validation {
+                pipeline: self.name.clone(),
+                message: e.to_string(),
+            });
+        }
+        Ok((recipe, pipeline))
+    }
+}
+
+fn reflected_workgroup_size(shader: &Shader, entry_point: &str) -> Option<[u32; 3]> {
+    shader.reflection.as_ref()?.entry_points.iter()
+        .find(|ep| ep.name == entry_point && ep.stage == naga::ShaderStage::Compute)
+        .map(|ep| ep.workgroup_size)
+}
+
+// ====== shared by the render and compute builders ======
+
+// wgsl goes through the preprocessor and naga first, anything else straight to wgpu
+fn compile_shader(skeleton: &AppSkeleton, defines: &Defines, label: &str, source: wgpu::ShaderSource) -> Result<Rc<Shader>, ShaderError> {
+    let wgpu::ShaderSource::Wgsl(code) = &source else {
+        return Ok(Rc::new(Shader::new(&skeleton.device, label, source)));
+    };
+    let preprocessed = skeleton.shader_library.preprocess_str(label, code, defines)?;
+    Ok(Rc::new(Shader::from_preprocessed(&skeleton.device, label, &preprocessed)?))
+}
+
+fn load_shader_file(skeleton: &AppSkeleton, defines: &Defines, mut file: ShaderFile) -> Result<Rc<Shader>, ShaderError> {
+    file.library.get_or_insert_with(|| skeleton.shader_library.clone());
+    // the file's own defines win
+    for (name, value) in defines {
+        file.defines.entry(name.clone()).or_insert_with(|| value.clone());
+    }
+    Ok(Rc::new(Shader::from_file(&skeleton.device, file)?))
+}
+
+// see PipelineBuilder::layout_from_reflection, stages without a shader are skipped
+fn reflected_pipeline_layout(
+    skeleton: &AppSkeleton,
+    layouts: &dyn BindGroupLayouts,
+    name: &str,
+    label: &str,
+    stages: &[(&Option<Rc<Shader>>, &str, naga::ShaderStage)],
+    slots: &[BindSlot],
+    push_constant_ranges: &[wgpu::PushConstantRange],
+) -> Result<(wgpu::PipelineLayout, Vec<BindSlot>), PipelineError> {
+    let mut reflected = ReflectedLayout::default();
+    for (shader, entry_point, stage) in stages {
+        let Some(shader) = shader else { continue };
+        let module = shader.reflection.as_ref().ok_or_else(|| PipelineError::Reflection {
+            pipeline: name.to_string(),
+            message: format!("the {:?} shader was not reflected (only wgsl is)", stage),
+        })?;
+        let info = shader.module_info.as_ref().ok_or_else(|| PipelineError::Reflection {
+            pipeline: name.to_string(),
+            message: format!("the {:?} shader does not validate", stage),
+        })?;
+        reflected.add_entry_point(module, info, entry_point, *stage)
+            .map_err(|message| PipelineError::Reflection { pipeline: name.to_string(), message })?;
+    }
+
+    let group_count = reflected.group_count().max(slots.len() as u32);
+    let bind_slots: Vec<BindSlot> = (0..group_count)
+        .map(|group| slots.get(group as usize).cloned().unwrap_or(BindSlot::Manual))
+        .collect();
+    let device = &skeleton.device;
+    let derived_layouts: Vec<Option<wgpu::BindGroupLayout>> = bind_slots.iter().zip(0..)
+        .map(|(slot, group)| (*slot == BindSlot::Manual).then(|| device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(format!("{} reflected bind group layout {}", name, group).as_str()),
+            entries: reflected.group(group),
+        })))
+        .collect();
+    let mut bind_group_layouts = Vec::with_capacity(bind_slots.len());
+    for ((slot, group), derived) in bind_slots.iter().zip(0..).zip(&derived_layouts) {
+        let mismatch = |message: String| PipelineError::BindGroupMismatch {
+            pipeline: name.to_string(),
+            group,
+            slot: slot.clone(),
+            message,
+        };
+        match derived {
+            Some(layout) => bind_group_layouts.push(layout),
+            None => {
+                // custom layouts aren't known here, wgpu checks those when building
+                if let Some(entries) = slot.layout_entries() {
+                    reflect::check_entries(reflected.group(group), entries).map_err(mismatch)?;
+                }
+                bind_group_layouts.push(layouts.bind_group_layout(slot)
+                    .ok_or_else(|| mismatch("the app has no such bind group".to_string()))?);
+            },
+        }
+    }
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts: &bind_group_layouts,
+        push_constant_ranges,
+    });
+    Ok((pipeline_layout, bind_slots))
+}
+
+// native wgpu resolves error scopes right away, so there is no need for a real executor
+fn poll_ready<F: std::future::Future>(future: F) -> Option<F::Output> {
+    use std::task::{Context, Poll, Waker};
+
+    let mut context = Context::from_waker(Waker::noop());
+    let mut future = std::pin::pin!(future);
+    match future.as_mut().poll(&mut context) {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
+}
+
+fn check_entry_point(pipeline: &str, shader: &Shader, entry_point: &str, stage: naga::ShaderStage) -> Result<(), PipelineError> {
+    if shader.has_entry_point(entry_point, stage) {
+        return Ok(());
+    }
+    let available = shader.entry_points().unwrap_or_default().into_iter()
+        .filter(|(_, s)| *s == stage)
+        .map(|(name, _)| name.to_string())
+        .collect();
+    Err(PipelineError::MissingEntryPoint {
+        pipeline: pipeline.to_string(),
+        entry_point: entry_point.to_string(),
+        stage,
+        available,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Dummy(&'static str);
+
+    impl RegistryPipeline for Dummy {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn reload_if_changed(&mut self, _device: &wgpu::Device) -> Option<Result<(), PipelineError>> {
+            None
+        }
+    }
+
+    #[test]
+    fn workgroup_counts_round_up() {
+        assert_eq!(workgroup_count(0, 64), 0);
+        assert_eq!(workgroup_count(1, 64), 1);
+        assert_eq!(workgroup_count(64, 64), 1);
+        assert_eq!(workgroup_count(65, 64), 2);
+        // a zero workgroup size counts as 1 instead of dividing by zero
+        assert_eq!(workgroup_count(5, 0), 5);
+        assert_eq!(workgroup_counts([1000, 17, 1], [64, 8, 1]), [16, 3, 1]);
+        assert_eq!(workgroup_counts([u32::MAX, 1, 1], [256, 1, 1]), [u32::MAX / 256 + 1, 1, 1]);
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        let mut registry = PipelineRegistry::new();
+        registry.insert(Dummy("a")).unwrap();
+        assert!(matches!(registry.insert(Dummy("a")), Err(PipelineError::DuplicateName(_))));
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn removed_slots_are_reused() {
+        let mut registry = PipelineRegistry::new();
+        let a = registry.insert(Dummy("a")).unwrap();
+        registry.insert(Dummy("b")).unwrap();
+        for _ in 0..10 {
+            assert!(registry.remove("a").is_some());
+            registry.insert(Dummy("a")).unwrap();
+        }
+        assert_eq!(registry.slots.len(), 2);
+
+        // the old handle doesn't reach the pipeline that took its slot
+        let new_a = registry.handle("a").unwrap();
+        assert_ne!(a, new_a);
+        assert!(registry.get_by_handle(a).is_none());
+        assert_eq!(registry.get_by_handle(new_a).map(|pipeline| pipeline.0), Some("a"));
+    }
+
+    #[test]
+    fn replace_keeps_the_handle() {
+        let mut registry = PipelineRegistry::new();
+        let handle = registry.insert(Dummy("a")).unwrap();
+        let (replaced, old) = registry.replace(Dummy("a"));
+        assert_eq!(handle, replaced);
+        assert!(old.is_some());
+        assert_eq!(registry.iter().count(), 1);
+    }
+}
@@ -0,0 +1,474 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::error::ShaderError;
+
+// name -> value, an empty value just marks the name as defined
+pub type Defines = BTreeMap<String, String>;
+
+// ====== built-in snippets ======
+// available to every library under fox/, see ShaderLibrary::new
+
+const CAMERA_WGSL: &str = "\
+// matches camera::CameraUniform
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+
+#ifdef CAMERA_GROUP
+@group(CAMERA_GROUP) @binding(0)
+var<uniform> camera: CameraUniform;
+#endif
+";
+
+const INSTANCE_WGSL: &str = "\
+// matches data::Instance, at locations 1-5
+struct InstanceInput {
+    @location(1) model_0: vec4<f32>,
+    @location(2) model_1: vec4<f32>,
+    @location(3) model_2: vec4<f32>,
+    @location(4) model_3: vec4<f32>,
+    @location(5) color: vec4<f32>,
+};
+
+fn instance_model(instance: InstanceInput) -> mat4x4<f32> {
+    return mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+}
+";
+
+const LIGHTING_WGSL: &str = "\
+struct DirectionalLight {
+    direction: vec3<f32>,
+    color: vec3<f32>,
+};
+
+fn lambert(normal: vec3<f32>, light: DirectionalLight) -> vec3<f32> {
+    return light.color * max(dot(normalize(normal), -normalize(light.direction)), 0.0);
+}
+
+fn blinn_phong(normal: vec3<f32>, view_dir: vec3<f32>, light: DirectionalLight, shininess: f32) -> vec3<f32> {
+    let half_dir = normalize(-normalize(light.direction) + normalize(view_dir));
+    let specular = pow(max(dot(normalize(normal), half_dir), 0.0), shininess);
+    return lambert(normal, light) + light.color * specular;
+}
+";
+
+// ====== library ======
+
+// where #include looks for files: next to the including file first, then the registered
+// virtual files, then the search paths in order
+#[derive(Clone, Debug)]
+pub struct ShaderLibrary {
+    pub files: HashMap<String, String>,
+    pub search_paths: Vec<PathBuf>,
+}
+
+impl Default for ShaderLibrary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShaderLibrary {
+    // with the built-in fox/ snippets
+    pub fn new() -> Self {
+        Self::empty()
+            .with_file("fox/camera.wgsl", CAMERA_WGSL)
+            .with_file("fox/instance.wgsl", INSTANCE_WGSL)
+            .with_file("fox/lighting.wgsl", LIGHTING_WGSL)
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            files: HashMap::new(),
+            search_paths: Vec::new(),
+        }
+    }
+
+    pub fn with_file(mut self, name: &str, source: &str) -> Self {
+        self.add_file(name, source);
+        self
+    }
+
+    pub fn with_search_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.search_paths.push(path.into());
+        self
+    }
+
+    pub fn add_file(&mut self, name: &str, source: &str) {
+        self.files.insert(name.to_string(), source.to_string());
+    }
+
+    pub fn preprocess_file(&self, path: &Path, defines: &Defines) -> Result<PreprocessedShader, ShaderError> {
+        let source = read_file(path)?;
+        let mut state = State::new(self, defines);
+        state.files.push(path.to_path_buf());
+        state.process(&path.display().to_string(), &file_key(path), path.parent(), &source)?;
+        Ok(state.finish())
+    }
+
+    // name is only used for diagnostics, includes resolve like virtual files
+    pub fn preprocess_str(&self, name: &str, source: &str, defines: &Defines) -> Result<PreprocessedShader, ShaderError> {
+        let mut state = State::new(self, defines);
+        state.process(name, name, None, source)?;
+        Ok(state.finish())
+    }
+
+    // (include-once key, source, path on disk)
+    fn resolve(&self, name: &str, dir: Option<&Path>) -> Result<Option<(String, String, Option<PathBuf>)>, ShaderError> {
+        let disk_candidates = dir.into_iter().chain(self.search_paths.iter().map(PathBuf::as_path));
+        for (index, base) in disk_candidates.enumerate() {
+            // the including file's directory beats virtual files, search paths don't
+            if index == 1 || (index == 0 && dir.is_none()) {
+                if let Some(source) = self.files.get(name) {
+                    return Ok(Some((format!("virtual:{}", name), source.clone(), None)));
+                }
+            }
+            let path = base.join(name);
+            if path.is_file() {
+                let source = read_file(&path)?;
+                return Ok(Some((file_key(&path), source, Some(path))));
+            }
+        }
+        Ok(self.files.get(name).map(|source| (format!("virtual:{}", name), source.clone(), None)))
+    }
+}
+
+// the same file reached through different relative paths is still the same file
+fn file_key(path: &Path) -> String {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf()).display().to_string()
+}
+
+fn read_file(path: &Path) -> Result<String, ShaderError> {
+    std::fs::read_to_string(path).map_err(|e| ShaderError::Io {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })
+}
+
+// ====== output ======
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLine {
+    pub file: String,
+    // 1-based
+    pub line: u32,
+}
+
+pub struct PreprocessedShader {
+    pub code: String,
+    // where each output line came from, line n is line_map[n - 1]
+    pub line_map: Vec<SourceLine>,
+    // every file read from disk (the main one and includes), for hot reload
+    pub files: Vec<PathBuf>,
+}
+
+impl PreprocessedShader {
+    pub fn source_line(&self, line: u32) -> Option<&SourceLine> {
+        self.line_map.get((line as usize).checked_sub(1)?)
+    }
+
+    // naga's check, with the error pointing at the original file and line
+    pub fn check(&self, name: &str) -> Result<naga::Module, ShaderError> {
+        self.validate(name).map(|(module, _)| module)
+    }
+
+    // check plus naga's ModuleInfo, for reflecting bind groups
+    pub(crate) fn validate(&self, name: &str) -> Result<(naga::Module, naga::valid::ModuleInfo), ShaderError> {
+        crate::shader::check_wgsl_at(&self.code, name, |line| self.source_line(line).cloned())
+    }
+}
+
+// ====== directive processing ======
+
+struct Conditional {
+    // this branch is being emitted
+    active: bool,
+    // some earlier branch already was
+    taken: bool,
+    in_else: bool,
+}
+
+struct State<'l> {
+    library: &'l ShaderLibrary,
+    defines: Defines,
+    code: String,
+    line_map: Vec<SourceLine>,
+    files: Vec<PathBuf>,
+    included: HashSet<String>,
+    // for include cycles
+    stack: Vec<String>,
+}
+
+impl<'l> State<'l> {
+    fn new(library: &'l ShaderLibrary, defines: &Defines) -> Self {
+        Self {
+            library,
+            defines: defines.clone(),
+            code: String::new(),
+            line_map: Vec::new(),
+            files: Vec::new(),
+            included: HashSet::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    fn finish(self) -> PreprocessedShader {
+        PreprocessedShader {
+            code: self.code,
+            line_map: self.line_map,
+            files: self.files,
+        }
+    }
+
+    fn process(&mut self, name: &str, key: &str, dir: Option<&Path>, source: &str) -> Result<(), ShaderError> {
+        self.included.insert(key.to_string());
+        self.stack.push(key.to_string());
+        let mut conditionals: Vec<Conditional> = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index as u32 + 1;
+            let error = |message: String| ShaderError::Preprocess {
+                file: name.to_string(),
+                line: line_number,
+                message,
+            };
+            let active = conditionals.last().is_none_or(|c| c.active);
+
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                if active {
+                    self.code.push_str(&substitute(line, &self.defines));
+                    self.code.push('\n');
+                    self.line_map.push(SourceLine {
+                        file: name.to_string(),
+                        line: line_number,
+                    });
+                }
+                continue;
+            };
+
+            let mut parts = directive.trim().splitn(2, char::is_whitespace);
+            let keyword = parts.next().unwrap_or("");
+            let argument = parts.next().unwrap_or("").trim();
+
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    let defined = self.defines.contains_key(argument);
+                    let condition = if keyword == "ifdef" { defined } else { !defined };
+                    conditionals.push(Conditional {
+                        active: active && condition,
+                        taken: condition,
+                        in_else: false,
+                    });
+                },
+                "else" => {
+                    let parent_active = conditionals.len() < 2 || conditionals[conditionals.len() - 2].active;
+                    let conditional = conditionals.last_mut()
+                        .filter(|c| !c.in_else)
+                        .ok_or_else(|| error("#else without #ifdef (or a second #else)".to_string()))?;
+                    conditional.active = parent_active && !conditional.taken;
+                    conditional.in_else = true;
+                },
+                "endif" => {
+                    conditionals.pop().ok_or_else(|| error("#endif without #ifdef".to_string()))?;
+                },
+                // everything below only counts in active blocks
+                _ if !active => {},
+                "define" => {
+                    let mut parts = argument.splitn(2, char::is_whitespace);
+                    let define = parts.next().filter(|d| !d.is_empty())
+                        .ok_or_else(|| error("#define without a name".to_string()))?;
+                    self.defines.insert(define.to_string(), parts.next().unwrap_or("").trim().to_string());
+                },
+                "undef" => {
+                    self.defines.remove(argument);
+                },
+                "include" => {
+                    let include = argument.strip_prefix('"').and_then(|a| a.strip_suffix('"'))
+                        .ok_or_else(|| error(format!("expected #include \"file\", got #include {}", argument)))?;
+                    let (key, source, path) = self.library.resolve(include, dir)?
+                        .ok_or_else(|| error(format!("cannot find include {}", include)))?;
+                    if self.stack.contains(&key) {
+                        return Err(error(format!("{} includes itself", include)));
+                    }
+                    // every file is only included once, so shared snippets can include each other
+                    if !self.included.contains(&key) {
+                        // disk files show up under their path in errors, virtual ones by name
+                        let include_name = path.as_ref().map_or(include.to_string(), |p| p.display().to_string());
+                        let include_dir = path.as_ref().and_then(|p| p.parent()).map(Path::to_path_buf);
+                        if let Some(path) = path {
+                            self.files.push(path);
+                        }
+                        self.process(&include_name, &key, include_dir.as_deref(), &source)?;
+                    }
+                },
+                _ => return Err(error(format!("unknown directive #{}", keyword))),
+            }
+        }
+
+        if !conditionals.is_empty() {
+            return Err(ShaderError::Preprocess {
+                file: name.to_string(),
+                line: source.lines().count() as u32,
+                message: "missing #endif".to_string(),
+            });
+        }
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+// replaces whole identifiers that have a (non empty) define value
+fn substitute(line: &str, defines: &Defines) -> String {
+    if defines.values().all(String::is_empty) {
+        return line.to_string();
+    }
+    let mut output = String::with_capacity(line.len());
+    let mut identifier = String::new();
+    let flush = |identifier: &mut String, output: &mut String| {
+        match defines.get(identifier.as_str()).filter(|value| !value.is_empty()) {
+            Some(value) => output.push_str(value),
+            None => output.push_str(identifier),
+        }
+        identifier.clear();
+    };
+    for c in line.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            identifier.push(c);
+        } else {
+            flush(&mut identifier, &mut output);
+            output.push(c);
+        }
+    }
+    flush(&mut identifier, &mut output);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a fresh directory under the system temp dir
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("preprocessor_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn lines(shader: &PreprocessedShader) -> Vec<&str> {
+        shader.code.lines().collect()
+    }
+
+    #[test]
+    fn include_resolution_order() {
+        let dir = temp_dir("order");
+        let search = dir.join("search");
+        std::fs::create_dir_all(&search).unwrap();
+        std::fs::write(dir.join("main.wgsl"), "#include \"a.wgsl\"\n#include \"b.wgsl\"\n#include \"c.wgsl\"\n").unwrap();
+        std::fs::write(dir.join("a.wgsl"), "// a next to main").unwrap();
+        std::fs::write(search.join("b.wgsl"), "// b in search path").unwrap();
+        std::fs::write(search.join("c.wgsl"), "// c in search path").unwrap();
+        let library = ShaderLibrary::empty()
+            .with_file("a.wgsl", "// a virtual")
+            .with_file("b.wgsl", "// b virtual")
+            .with_search_path(&search);
+
+        let shader = library.preprocess_file(&dir.join("main.wgsl"), &Defines::new()).unwrap();
+        // next to the including file, then virtual files, then search paths
+        assert_eq!(lines(&shader), ["// a next to main", "// b virtual", "// c in search path"]);
+        assert_eq!(shader.files.len(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn includes_only_once() {
+        let library = ShaderLibrary::empty()
+            .with_file("common.wgsl", "// common")
+            .with_file("a.wgsl", "#include \"common.wgsl\"\n// a");
+        let shader = library.preprocess_str("main", "#include \"common.wgsl\"\n#include \"a.wgsl\"", &Defines::new()).unwrap();
+        assert_eq!(lines(&shader), ["// common", "// a"]);
+    }
+
+    #[test]
+    fn nested_conditionals() {
+        let source = "\
+#ifdef A
+a
+#ifdef B
+ab
+#else
+a not b
+#endif
+#else
+not a
+#ifndef B
+not a not b
+#endif
+#endif
+";
+        let run = |defines: &[&str]| {
+            let defines = defines.iter().map(|d| (d.to_string(), String::new())).collect();
+            ShaderLibrary::empty().preprocess_str("main", source, &defines).unwrap().code
+        };
+        assert_eq!(run(&["A", "B"]), "a\nab\n");
+        assert_eq!(run(&["A"]), "a\na not b\n");
+        assert_eq!(run(&["B"]), "not a\n");
+        assert_eq!(run(&[]), "not a\nnot a not b\n");
+    }
+
+    #[test]
+    fn bad_conditionals_are_errors() {
+        let library = ShaderLibrary::empty();
+        for source in ["#else", "#endif", "#ifdef A\n#else\n#else\n#endif", "#ifdef A"] {
+            assert!(matches!(library.preprocess_str("main", source, &Defines::new()), Err(ShaderError::Preprocess { .. })), "{}", source);
+        }
+    }
+
+    #[test]
+    fn defines_are_substituted() {
+        let mut defines = Defines::new();
+        defines.insert("GROUP".to_string(), "2".to_string());
+        let shader = ShaderLibrary::empty().preprocess_str("main", "@group(GROUP) GROUPS\n#define SIZE 64\nSIZE", &defines).unwrap();
+        assert_eq!(lines(&shader), ["@group(2) GROUPS", "64"]);
+    }
+
+    #[test]
+    fn include_cycles_are_errors() {
+        let library = ShaderLibrary::empty()
+            .with_file("a.wgsl", "#include \"b.wgsl\"")
+            .with_file("b.wgsl", "// b\n#include \"a.wgsl\"");
+        match library.preprocess_str("main", "#include \"a.wgsl\"", &Defines::new()) {
+            Err(ShaderError::Preprocess { file, line, message }) => {
+                assert_eq!(file, "b.wgsl");
+                assert_eq!(line, 2);
+                assert!(message.contains("includes itself"), "{}", message);
+            },
+            _ => panic!("expected an include cycle error"),
+        }
+    }
+
+    #[test]
+    fn line_map_points_at_the_original_lines() {
+        let library = ShaderLibrary::empty().with_file("inc.wgsl", "// inc 1\n#ifdef NOPE\nskipped\n#endif\n// inc 5");
+        let shader = library.preprocess_str("main", "// main 1\n#include \"inc.wgsl\"\n// main 3", &Defines::new()).unwrap();
+        let line = |file: &str, line| SourceLine { file: file.to_string(), line };
+        assert_eq!(shader.line_map, [line("main", 1), line("inc.wgsl", 1), line("inc.wgsl", 5), line("main", 3)]);
+        assert_eq!(shader.source_line(3), Some(&line("inc.wgsl", 5)));
+        assert_eq!(shader.source_line(0), None);
+        assert_eq!(shader.source_line(5), None);
+    }
+
+    #[test]
+    fn errors_point_at_the_original_file() {
+        let library = ShaderLibrary::empty().with_file("inc.wgsl", "// fine\nfn broken( {}");
+        let shader = library.preprocess_str("main", "#include \"inc.wgsl\"", &Defines::new()).unwrap();
+        match shader.check("main") {
+            Err(ShaderError::Compile { diagnostic, .. }) => {
+                assert!(diagnostic.starts_with("at inc.wgsl:2:"), "{}", diagnostic);
+                assert!(diagnostic.contains("main (preprocessed)"), "{}", diagnostic);
+            },
+            _ => panic!("expected a compile error"),
+        }
+    }
+}
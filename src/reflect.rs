@@ -0,0 +1,497 @@
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+
+use crate::pipeline::OwnedVertexBufferLayout;
+
+// layouts worked out from naga modules, so they don't have to be written by hand. errors are
+// plain messages, PipelineBuilder wraps them with the pipeline name
+
+// ====== bind groups ======
+
+// every @group/@binding used by the entry points added so far, visibility is the union of the
+// stages that use each binding
+#[derive(Clone, Debug, Default)]
+pub struct ReflectedLayout {
+    pub groups: BTreeMap<u32, Vec<wgpu::BindGroupLayoutEntry>>,
+}
+
+impl ReflectedLayout {
+    // entry points that don't exist are skipped, PipelineBuilder::validate reports those. only
+    // the validator knows which globals an entry point actually touches, so this needs the
+    // module's ModuleInfo (see Shader::module_info)
+    pub fn add_entry_point(&mut self, module: &naga::Module, info: &naga::valid::ModuleInfo, entry_point: &str, stage: naga::ShaderStage) -> Result<(), String> {
+        let Some(index) = module.entry_points.iter()
+            .position(|ep| ep.name == entry_point && ep.stage == stage) else {
+            return Ok(());
+        };
+        let function_info = info.get_entry_point(index);
+
+        for (handle, variable) in module.global_variables.iter() {
+            let Some(binding) = &variable.binding else { continue };
+            if function_info[handle].is_empty() {
+                continue;
+            }
+            let (ty, count) = binding_type(module, variable)
+                .map_err(|message| format!("@group({}) @binding({}): {}", binding.group, binding.binding, message))?;
+            let entries = self.groups.entry(binding.group).or_default();
+            match entries.iter_mut().find(|entry| entry.binding == binding.binding) {
+                Some(entry) if entry.ty == ty && entry.count == count => entry.visibility |= shader_stages(stage),
+                Some(_) => return Err(format!("@group({}) @binding({}) has a different type in each stage", binding.group, binding.binding)),
+                None => entries.push(wgpu::BindGroupLayoutEntry {
+                    binding: binding.binding,
+                    visibility: shader_stages(stage),
+                    ty,
+                    count,
+                }),
+            }
+            entries.sort_by_key(|entry| entry.binding);
+        }
+        Ok(())
+    }
+
+    // empty for groups the shaders don't use
+    pub fn group(&self, group: u32) -> &[wgpu::BindGroupLayoutEntry] {
+        self.groups.get(&group).map_or(&[], Vec::as_slice)
+    }
+
+    // highest group used plus one, gaps count as empty groups
+    pub fn group_count(&self) -> u32 {
+        self.groups.keys().next_back().map_or(0, |group| group + 1)
+    }
+}
+
+// every entry the shader uses has to be in the layout with the same type and (at least) its
+// visibility, layout entries the shader doesn't use are fine
+pub fn check_entries(reflected: &[wgpu::BindGroupLayoutEntry], layout: &[wgpu::BindGroupLayoutEntry]) -> Result<(), String> {
+    for entry in reflected {
+        let Some(expected) = layout.iter().find(|expected| expected.binding == entry.binding) else {
+            return Err(format!("shader uses @binding({}), which the layout does not have", entry.binding));
+        };
+        if !same_binding_type(&entry.ty, &expected.ty) || entry.count != expected.count {
+            return Err(format!("@binding({}) is {:?} in the shader, but {:?} in the layout", entry.binding, entry.ty, expected.ty));
+        }
+        if !expected.visibility.contains(entry.visibility) {
+            return Err(format!("@binding({}) is used by {:?}, but only visible to {:?}", entry.binding, entry.visibility, expected.visibility));
+        }
+    }
+    Ok(())
+}
+
+// the shader can't say whether a float texture is filterable or how big a buffer must be, so
+// those are left to the layout
+fn same_binding_type(shader: &wgpu::BindingType, layout: &wgpu::BindingType) -> bool {
+    match (shader, layout) {
+        (
+            wgpu::BindingType::Buffer { ty, has_dynamic_offset: _, min_binding_size: _ },
+            wgpu::BindingType::Buffer { ty: layout_ty, .. },
+        ) => ty == layout_ty,
+        (
+            wgpu::BindingType::Texture { sample_type: wgpu::TextureSampleType::Float { .. }, view_dimension, multisampled },
+            wgpu::BindingType::Texture { sample_type: wgpu::TextureSampleType::Float { .. }, view_dimension: layout_dimension, multisampled: layout_multisampled },
+        ) => view_dimension == layout_dimension && multisampled == layout_multisampled,
+        // filtering and non filtering samplers look the same in wgsl
+        (
+            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering | wgpu::SamplerBindingType::NonFiltering),
+            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering | wgpu::SamplerBindingType::NonFiltering),
+        ) => true,
+        _ => shader == layout,
+    }
+}
+
+fn shader_stages(stage: naga::ShaderStage) -> wgpu::ShaderStages {
+    match stage {
+        naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+        naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+        naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+    }
+}
+
+fn binding_type(module: &naga::Module, variable: &naga::GlobalVariable) -> Result<(wgpu::BindingType, Option<NonZeroU32>), String> {
+    let (ty, count) = match module.types[variable.ty].inner {
+        naga::TypeInner::BindingArray { base, size } => (base, Some(array_size(module, size)?)),
+        _ => (variable.ty, None),
+    };
+    let binding_type = match variable.space {
+        naga::AddressSpace::Uniform => wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        naga::AddressSpace::Storage { access } => wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: !access.contains(naga::StorageAccess::STORE) },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        naga::AddressSpace::Handle => match module.types[ty].inner {
+            naga::TypeInner::Sampler { comparison: true } => wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+            naga::TypeInner::Sampler { comparison: false } => wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            naga::TypeInner::Image { dim, arrayed, class } => image_binding_type(dim, arrayed, class)?,
+            ref other => return Err(format!("unsupported handle type {:?}", other)),
+        },
+        other => return Err(format!("unsupported address space {:?}", other)),
+    };
+    Ok((binding_type, count))
+}
+
+fn array_size(module: &naga::Module, size: naga::ArraySize) -> Result<NonZeroU32, String> {
+    let naga::ArraySize::Constant(constant) = size else {
+        return Err("binding arrays need a fixed size".to_string());
+    };
+    let length = match module.constants[constant].inner {
+        naga::ConstantInner::Scalar { value: naga::ScalarValue::Uint(length), .. } => Some(length as u32),
+        naga::ConstantInner::Scalar { value: naga::ScalarValue::Sint(length), .. } => Some(length as u32),
+        _ => None,
+    };
+    length.and_then(NonZeroU32::new).ok_or_else(|| "binding array size is not a positive integer".to_string())
+}
+
+fn image_binding_type(dim: naga::ImageDimension, arrayed: bool, class: naga::ImageClass) -> Result<wgpu::BindingType, String> {
+    let view_dimension = match (dim, arrayed) {
+        (naga::ImageDimension::D1, false) => wgpu::TextureViewDimension::D1,
+        (naga::ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+        (naga::ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+        (naga::ImageDimension::D3, false) => wgpu::TextureViewDimension::D3,
+        (naga::ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+        (naga::ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+        (dim, true) => return Err(format!("{:?} textures can't be arrayed", dim)),
+    };
+    Ok(match class {
+        naga::ImageClass::Sampled { kind, multi } => wgpu::BindingType::Texture {
+            sample_type: match kind {
+                // multisampled textures can't be filtered
+                naga::ScalarKind::Float => wgpu::TextureSampleType::Float { filterable: !multi },
+                naga::ScalarKind::Sint => wgpu::TextureSampleType::Sint,
+                naga::ScalarKind::Uint => wgpu::TextureSampleType::Uint,
+                naga::ScalarKind::Bool => return Err("bool textures don't exist".to_string()),
+            },
+            view_dimension,
+            multisampled: multi,
+        },
+        naga::ImageClass::Depth { multi } => wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Depth,
+            view_dimension,
+            multisampled: multi,
+        },
+        naga::ImageClass::Storage { format, access } => wgpu::BindingType::StorageTexture {
+            access: if access.contains(naga::StorageAccess::LOAD | naga::StorageAccess::STORE) {
+                wgpu::StorageTextureAccess::ReadWrite
+            } else if access.contains(naga::StorageAccess::STORE) {
+                wgpu::StorageTextureAccess::WriteOnly
+            } else {
+                wgpu::StorageTextureAccess::ReadOnly
+            },
+            format: storage_format(format),
+            view_dimension,
+        },
+    })
+}
+
+fn storage_format(format: naga::StorageFormat) -> wgpu::TextureFormat {
+    use naga::StorageFormat as Sf;
+    use wgpu::TextureFormat as Tf;
+    match format {
+        Sf::R8Unorm => Tf::R8Unorm,
+        Sf::R8Snorm => Tf::R8Snorm,
+        Sf::R8Uint => Tf::R8Uint,
+        Sf::R8Sint => Tf::R8Sint,
+        Sf::R16Uint => Tf::R16Uint,
+        Sf::R16Sint => Tf::R16Sint,
+        Sf::R16Float => Tf::R16Float,
+        Sf::Rg8Unorm => Tf::Rg8Unorm,
+        Sf::Rg8Snorm => Tf::Rg8Snorm,
+        Sf::Rg8Uint => Tf::Rg8Uint,
+        Sf::Rg8Sint => Tf::Rg8Sint,
+        Sf::R32Uint => Tf::R32Uint,
+        Sf::R32Sint => Tf::R32Sint,
+        Sf::R32Float => Tf::R32Float,
+        Sf::Rg16Uint => Tf::Rg16Uint,
+        Sf::Rg16Sint => Tf::Rg16Sint,
+        Sf::Rg16Float => Tf::Rg16Float,
+        Sf::Rgba8Unorm => Tf::Rgba8Unorm,
+        Sf::Rgba8Snorm => Tf::Rgba8Snorm,
+        Sf::Rgba8Uint => Tf::Rgba8Uint,
+        Sf::Rgba8Sint => Tf::Rgba8Sint,
+        Sf::Rgb10a2Unorm => Tf::Rgb10a2Unorm,
+        Sf::Rg11b10Float => Tf::Rg11b10Float,
+        Sf::Rg32Uint => Tf::Rg32Uint,
+        Sf::Rg32Sint => Tf::Rg32Sint,
+        Sf::Rg32Float => Tf::Rg32Float,
+        Sf::Rgba16Uint => Tf::Rgba16Uint,
+        Sf::Rgba16Sint => Tf::Rgba16Sint,
+        Sf::Rgba16Float => Tf::Rgba16Float,
+        Sf::Rgba32Uint => Tf::Rgba32Uint,
+        Sf::Rgba32Sint => Tf::Rgba32Sint,
+        Sf::Rgba32Float => Tf::Rgba32Float,
+        Sf::R16Unorm => Tf::R16Unorm,
+        Sf::R16Snorm => Tf::R16Snorm,
+        Sf::Rg16Unorm => Tf::Rg16Unorm,
+        Sf::Rg16Snorm => Tf::Rg16Snorm,
+        Sf::Rgba16Unorm => Tf::Rgba16Unorm,
+        Sf::Rgba16Snorm => Tf::Rgba16Snorm,
+    }
+}
+
+// ====== vertex inputs ======
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VertexInput {
+    pub location: u32,
+    pub kind: naga::ScalarKind,
+    // None for types a vertex buffer can't feed (matrices, bools, ...)
+    pub format: Option<wgpu::VertexFormat>,
+}
+
+// the @location inputs of a vertex entry point (loose arguments and struct members), by location
+pub fn vertex_inputs(module: &naga::Module, entry_point: &str) -> Vec<VertexInput> {
+    let Some(entry_point) = module.entry_points.iter()
+        .find(|ep| ep.name == entry_point && ep.stage == naga::ShaderStage::Vertex) else {
+        return Vec::new();
+    };
+    let mut inputs = Vec::new();
+    for argument in &entry_point.function.arguments {
+        match (&argument.binding, &module.types[argument.ty].inner) {
+            (Some(naga::Binding::Location { location, .. }), inner) => inputs.extend(vertex_input(*location, inner)),
+            (None, naga::TypeInner::Struct { members, .. }) => {
+                for member in members {
+                    if let Some(naga::Binding::Location { location, .. }) = member.binding {
+                        inputs.extend(vertex_input(location, &module.types[member.ty].inner));
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+    inputs.sort_by_key(|input| input.location);
+    inputs
+}
+
+fn vertex_input(location: u32, inner: &naga::TypeInner) -> Option<VertexInput> {
+    use naga::ScalarKind as Sk;
+    use naga::VectorSize as Vs;
+    use wgpu::VertexFormat as Vf;
+    let (kind, width, size) = match *inner {
+        naga::TypeInner::Scalar { kind, width } => (kind, width, None),
+        naga::TypeInner::Vector { size, kind, width } => (kind, width, Some(size)),
+        _ => return None,
+    };
+    let format = match (kind, width, size) {
+        (Sk::Float, 4, None) => Some(Vf::Float32),
+        (Sk::Float, 4, Some(Vs::Bi)) => Some(Vf::Float32x2),
+        (Sk::Float, 4, Some(Vs::Tri)) => Some(Vf::Float32x3),
+        (Sk::Float, 4, Some(Vs::Quad)) => Some(Vf::Float32x4),
+        (Sk::Float, 8, None) => Some(Vf::Float64),
+        (Sk::Float, 8, Some(Vs::Bi)) => Some(Vf::Float64x2),
+        (Sk::Float, 8, Some(Vs::Tri)) => Some(Vf::Float64x3),
+        (Sk::Float, 8, Some(Vs::Quad)) => Some(Vf::Float64x4),
+        (Sk::Sint, 4, None) => Some(Vf::Sint32),
+        (Sk::Sint, 4, Some(Vs::Bi)) => Some(Vf::Sint32x2),
+        (Sk::Sint, 4, Some(Vs::Tri)) => Some(Vf::Sint32x3),
+        (Sk::Sint, 4, Some(Vs::Quad)) => Some(Vf::Sint32x4),
+        (Sk::Uint, 4, None) => Some(Vf::Uint32),
+        (Sk::Uint, 4, Some(Vs::Bi)) => Some(Vf::Uint32x2),
+        (Sk::Uint, 4, Some(Vs::Tri)) => Some(Vf::Uint32x3),
+        (Sk::Uint, 4, Some(Vs::Quad)) => Some(Vf::Uint32x4),
+        _ => None,
+    };
+    Some(VertexInput {
+        location,
+        kind,
+        format,
+    })
+}
+
+// one tightly packed per vertex buffer holding every input in location order
+pub fn vertex_buffer_layout(inputs: &[VertexInput]) -> Result<OwnedVertexBufferLayout, String> {
+    let mut offset = 0;
+    let mut attributes = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let format = input.format
+            .ok_or_else(|| format!("@location({}) has a type no vertex format matches", input.location))?;
+        attributes.push(wgpu::VertexAttribute {
+            format,
+            offset,
+            shader_location: input.location,
+        });
+        offset += format.size();
+    }
+    Ok(OwnedVertexBufferLayout {
+        array_stride: offset,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes,
+    })
+}
+
+// every input needs an attribute at its location with the same kind of scalar, wgpu allows
+// the component counts to differ
+pub fn check_vertex_buffers(inputs: &[VertexInput], layouts: &[OwnedVertexBufferLayout]) -> Result<(), (u32, String)> {
+    for input in inputs {
+        let attribute = layouts.iter()
+            .flat_map(|layout| &layout.attributes)
+            .find(|attribute| attribute.shader_location == input.location)
+            .ok_or_else(|| (input.location, "no vertex buffer layout has an attribute there".to_string()))?;
+        if format_kind(attribute.format) != input.kind {
+            return Err((input.location, format!("the shader reads {:?}, but the vertex buffer has {:?}", input.kind, attribute.format)));
+        }
+    }
+    Ok(())
+}
+
+fn format_kind(format: wgpu::VertexFormat) -> naga::ScalarKind {
+    use wgpu::VertexFormat as Vf;
+    match format {
+        Vf::Uint8x2 | Vf::Uint8x4 | Vf::Uint16x2 | Vf::Uint16x4
+        | Vf::Uint32 | Vf::Uint32x2 | Vf::Uint32x3 | Vf::Uint32x4 => naga::ScalarKind::Uint,
+        Vf::Sint8x2 | Vf::Sint8x4 | Vf::Sint16x2 | Vf::Sint16x4
+        | Vf::Sint32 | Vf::Sint32x2 | Vf::Sint32x3 | Vf::Sint32x4 => naga::ScalarKind::Sint,
+        _ => naga::ScalarKind::Float,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(code: &str) -> naga::Module {
+        naga::front::wgsl::parse_str(code).unwrap()
+    }
+
+    // the binding type of the global named name
+    fn global_binding_type(module: &naga::Module, name: &str) -> Result<(wgpu::BindingType, Option<NonZeroU32>), String> {
+        let (_, variable) = module.global_variables.iter()
+            .find(|(_, variable)| variable.name.as_deref() == Some(name))
+            .unwrap();
+        binding_type(module, variable)
+    }
+
+    fn reflect(code: &str, entry_point: &str, stage: naga::ShaderStage) -> ReflectedLayout {
+        let module = parse(code);
+        let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+            .validate(&module)
+            .unwrap();
+        let mut layout = ReflectedLayout::default();
+        layout.add_entry_point(&module, &info, entry_point, stage).unwrap();
+        layout
+    }
+
+    #[test]
+    fn binding_types() {
+        let module = parse("
+            struct Data { values: array<f32> };
+            @group(0) @binding(0) var<uniform> uniform_data: vec4<f32>;
+            @group(0) @binding(1) var<storage, read> read_data: Data;
+            @group(0) @binding(2) var<storage, read_write> write_data: Data;
+            @group(1) @binding(0) var color: texture_2d<f32>;
+            @group(1) @binding(1) var color_sampler: sampler;
+            @group(1) @binding(2) var shadow: texture_depth_2d_array;
+            @group(1) @binding(3) var shadow_sampler: sampler_comparison;
+            @group(1) @binding(4) var output: texture_storage_2d<rgba8unorm, write>;
+            @group(1) @binding(5) var samples: texture_multisampled_2d<u32>;
+        ");
+        let buffer = |ty| wgpu::BindingType::Buffer { ty, has_dynamic_offset: false, min_binding_size: None };
+        let texture = |sample_type, view_dimension, multisampled| wgpu::BindingType::Texture { sample_type, view_dimension, multisampled };
+        let cases = [
+            ("uniform_data", buffer(wgpu::BufferBindingType::Uniform)),
+            ("read_data", buffer(wgpu::BufferBindingType::Storage { read_only: true })),
+            ("write_data", buffer(wgpu::BufferBindingType::Storage { read_only: false })),
+            ("color", texture(wgpu::TextureSampleType::Float { filterable: true }, wgpu::TextureViewDimension::D2, false)),
+            ("color_sampler", wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)),
+            ("shadow", texture(wgpu::TextureSampleType::Depth, wgpu::TextureViewDimension::D2Array, false)),
+            ("shadow_sampler", wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison)),
+            ("output", wgpu::BindingType::StorageTexture {
+                access: wgpu::StorageTextureAccess::WriteOnly,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            }),
+            ("samples", texture(wgpu::TextureSampleType::Uint, wgpu::TextureViewDimension::D2, true)),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(global_binding_type(&module, name), Ok((expected, None)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn only_used_bindings_are_reflected() {
+        let code = "
+            @group(0) @binding(0) var<uniform> used: vec4<f32>;
+            @group(0) @binding(1) var<uniform> unused: vec4<f32>;
+            @group(2) @binding(3) var<uniform> also_used: vec4<f32>;
+            @vertex fn vs_main() -> @builtin(position) vec4<f32> { return used + also_used; }
+        ";
+        let layout = reflect(code, "vs_main", naga::ShaderStage::Vertex);
+        assert_eq!(layout.group(0).iter().map(|entry| entry.binding).collect::<Vec<_>>(), [0]);
+        assert!(layout.group(1).is_empty());
+        assert_eq!(layout.group(2)[0].binding, 3);
+        assert_eq!(layout.group(2)[0].visibility, wgpu::ShaderStages::VERTEX);
+        assert_eq!(layout.group_count(), 3);
+    }
+
+    #[test]
+    fn entries_are_checked_against_the_layout() {
+        let code = "
+            @group(0) @binding(0) var<uniform> camera: mat4x4<f32>;
+            @group(0) @binding(1) var color: texture_2d<f32>;
+            @group(0) @binding(2) var color_sampler: sampler;
+            @fragment fn fs_main() -> @location(0) vec4<f32> {
+                return camera * textureSample(color, color_sampler, vec2<f32>(0.0));
+            }
+        ";
+        let reflected = reflect(code, "fs_main", naga::ShaderStage::Fragment);
+        let entry = |binding, visibility, ty| wgpu::BindGroupLayoutEntry { binding, visibility, ty, count: None };
+        let uniform = wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None };
+        let texture = wgpu::BindingType::Texture {
+            // the shader can't tell, the layout decides
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        };
+        let sampler = wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering);
+        let all = wgpu::ShaderStages::VERTEX_FRAGMENT;
+
+        let layout = [entry(0, all, uniform), entry(1, all, texture), entry(2, all, sampler), entry(3, all, uniform)];
+        assert_eq!(check_entries(reflected.group(0), &layout), Ok(()));
+        // missing binding
+        assert!(check_entries(reflected.group(0), &layout[..2]).is_err());
+        // not visible to the fragment stage
+        let layout = [entry(0, wgpu::ShaderStages::VERTEX, uniform), entry(1, all, texture), entry(2, all, sampler)];
+        assert!(check_entries(reflected.group(0), &layout).is_err());
+        // storage where the shader has a uniform
+        let storage = wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None };
+        let layout = [entry(0, all, storage), entry(1, all, texture), entry(2, all, sampler)];
+        assert!(check_entries(reflected.group(0), &layout).is_err());
+    }
+
+    #[test]
+    fn vertex_buffers_are_checked_against_the_inputs() {
+        let module = parse("
+            struct VertexInput {
+                @location(0) position: vec3<f32>,
+                @location(2) id: u32,
+            };
+            @vertex fn vs_main(vertex: VertexInput, @location(1) uv: vec2<f32>) -> @builtin(position) vec4<f32> {
+                return vec4<f32>(vertex.position, f32(vertex.id) + uv.x);
+            }
+        ");
+        let inputs = vertex_inputs(&module, "vs_main");
+        assert_eq!(inputs.iter().map(|input| input.location).collect::<Vec<_>>(), [0, 1, 2]);
+
+        let layout = vertex_buffer_layout(&inputs).unwrap();
+        assert_eq!(layout.array_stride, 12 + 8 + 4);
+        assert_eq!(layout.attributes.iter().map(|attribute| attribute.offset).collect::<Vec<_>>(), [0, 12, 20]);
+        assert_eq!(check_vertex_buffers(&inputs, std::slice::from_ref(&layout)), Ok(()));
+
+        // uv in a second buffer, with fewer components than the shader reads
+        let mut split = layout.clone();
+        split.attributes.remove(1);
+        let uv = OwnedVertexBufferLayout {
+            array_stride: 4,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: vec![wgpu::VertexAttribute { format: wgpu::VertexFormat::Float32, offset: 0, shader_location: 1 }],
+        };
+        assert_eq!(check_vertex_buffers(&inputs, &[split.clone(), uv.clone()]), Ok(()));
+        assert_eq!(check_vertex_buffers(&inputs, &[split.clone()]).map_err(|(location, _)| location), Err(1));
+
+        // the id fed from floats
+        split.attributes[1].format = wgpu::VertexFormat::Float32;
+        assert_eq!(check_vertex_buffers(&inputs, &[split, uv]).map_err(|(location, _)| location), Err(2));
+    }
+}
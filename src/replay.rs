@@ -0,0 +1,544 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{
+        DeviceEvent, DeviceId, ElementState, KeyboardInput, ModifiersState, MouseButton,
+        MouseScrollDelta, TouchPhase, WindowEvent,
+    },
+};
+
+use crate::action::{key_from_name, QUIT_ACTION};
+use crate::app::{AppSkeleton, Application};
+
+// ====== recorded events ======
+
+// the subset of window events that matter for driving an app, everything else (file drops,
+// ime, touch...) isn't recorded
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordedInput {
+    Keyboard {
+        scancode: u32,
+        keycode: Option<winit::event::VirtualKeyCode>,
+        state: ElementState,
+    },
+    MouseButton {
+        button: MouseButton,
+        state: ElementState,
+    },
+    CursorMoved {
+        x: f64,
+        y: f64,
+    },
+    CursorEntered,
+    CursorLeft,
+    MouseWheel {
+        x: f32,
+        y: f32,
+        // PixelDelta instead of LineDelta
+        pixels: bool,
+    },
+    // raw device motion
+    MouseMotion {
+        x: f64,
+        y: f64,
+    },
+    Modifiers(ModifiersState),
+    Character(char),
+    Focused(bool),
+    Resized {
+        width: u32,
+        height: u32,
+    },
+    CloseRequested,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedEvent {
+    // the frame the event was delivered before
+    pub frame: u64,
+    // since the recording started
+    pub time: Duration,
+    pub input: RecordedInput,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recording {
+    pub events: Vec<RecordedEvent>,
+    // delta time of every frame, in order
+    pub frames: Vec<Duration>,
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(std::io::Error),
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordingError::Io(e) => write!(f, "failed to read recording: {}", e),
+            RecordingError::Parse { line, message } => write!(f, "recording line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+impl From<std::io::Error> for RecordingError {
+    fn from(e: std::io::Error) -> Self {
+        RecordingError::Io(e)
+    }
+}
+
+impl RecordedInput {
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        Some(match event {
+            WindowEvent::KeyboardInput { input, .. } => RecordedInput::Keyboard {
+                scancode: input.scancode,
+                keycode: input.virtual_keycode,
+                state: input.state,
+            },
+            WindowEvent::MouseInput { state, button, .. } => RecordedInput::MouseButton {
+                button: *button,
+                state: *state,
+            },
+            WindowEvent::CursorMoved { position, .. } => RecordedInput::CursorMoved {
+                x: position.x,
+                y: position.y,
+            },
+            WindowEvent::CursorEntered { .. } => RecordedInput::CursorEntered,
+            WindowEvent::CursorLeft { .. } => RecordedInput::CursorLeft,
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(x, y) => RecordedInput::MouseWheel { x: *x, y: *y, pixels: false },
+                MouseScrollDelta::PixelDelta(p) => RecordedInput::MouseWheel {
+                    x: p.x as f32,
+                    y: p.y as f32,
+                    pixels: true,
+                },
+            },
+            WindowEvent::ModifiersChanged(modifiers) => RecordedInput::Modifiers(*modifiers),
+            WindowEvent::ReceivedCharacter(c) => RecordedInput::Character(*c),
+            WindowEvent::Focused(focused) => RecordedInput::Focused(*focused),
+            WindowEvent::Resized(size) => RecordedInput::Resized {
+                width: size.width,
+                height: size.height,
+            },
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => RecordedInput::Resized {
+                width: new_inner_size.width,
+                height: new_inner_size.height,
+            },
+            WindowEvent::CloseRequested => RecordedInput::CloseRequested,
+            _ => return None,
+        })
+    }
+
+    pub fn from_device_event(event: &DeviceEvent) -> Option<Self> {
+        match event {
+            DeviceEvent::MouseMotion { delta: (x, y) } => Some(RecordedInput::MouseMotion { x: *x, y: *y }),
+            _ => None,
+        }
+    }
+
+    // None for device events, see device_event()
+    #[allow(deprecated)]
+    pub fn to_window_event(&self) -> Option<WindowEvent<'static>> {
+        // recordings dont know (or care) which device anything came from
+        let device_id = unsafe { DeviceId::dummy() };
+        Some(match *self {
+            RecordedInput::Keyboard { scancode, keycode, state } => WindowEvent::KeyboardInput {
+                device_id,
+                input: KeyboardInput {
+                    scancode,
+                    state,
+                    virtual_keycode: keycode,
+                    modifiers: ModifiersState::empty(),
+                },
+                is_synthetic: false,
+            },
+            RecordedInput::MouseButton { button, state } => WindowEvent::MouseInput {
+                device_id,
+                state,
+                button,
+                modifiers: ModifiersState::empty(),
+            },
+            RecordedInput::CursorMoved { x, y } => WindowEvent::CursorMoved {
+                device_id,
+                position: PhysicalPosition::new(x, y),
+                modifiers: ModifiersState::empty(),
+            },
+            RecordedInput::CursorEntered => WindowEvent::CursorEntered { device_id },
+            RecordedInput::CursorLeft => WindowEvent::CursorLeft { device_id },
+            RecordedInput::MouseWheel { x, y, pixels } => WindowEvent::MouseWheel {
+                device_id,
+                delta: if pixels {
+                    MouseScrollDelta::PixelDelta(PhysicalPosition::new(x as f64, y as f64))
+                } else {
+                    MouseScrollDelta::LineDelta(x, y)
+                },
+                phase: TouchPhase::Moved,
+                modifiers: ModifiersState::empty(),
+            },
+            RecordedInput::MouseMotion { .. } => return None,
+            RecordedInput::Modifiers(modifiers) => WindowEvent::ModifiersChanged(modifiers),
+            RecordedInput::Character(c) => WindowEvent::ReceivedCharacter(c),
+            RecordedInput::Focused(focused) => WindowEvent::Focused(focused),
+            RecordedInput::Resized { width, height } => WindowEvent::Resized(PhysicalSize::new(width, height)),
+            RecordedInput::CloseRequested => WindowEvent::CloseRequested,
+        })
+    }
+
+    pub fn to_device_event(&self) -> Option<DeviceEvent> {
+        match *self {
+            RecordedInput::MouseMotion { x, y } => Some(DeviceEvent::MouseMotion { delta: (x, y) }),
+            _ => None,
+        }
+    }
+}
+
+// one event per line, whitespace separated: `<kind> <fields...>`
+impl fmt::Display for RecordedInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordedInput::Keyboard { scancode, keycode, state } => match keycode {
+                Some(keycode) => write!(f, "key {} {:?} {}", scancode, keycode, state_name(*state)),
+                None => write!(f, "key {} - {}", scancode, state_name(*state)),
+            },
+            RecordedInput::MouseButton { button, state } => match button {
+                MouseButton::Other(n) => write!(f, "mouse {} {}", n, state_name(*state)),
+                button => write!(f, "mouse {:?} {}", button, state_name(*state)),
+            },
+            RecordedInput::CursorMoved { x, y } => write!(f, "cursor {} {}", x, y),
+            RecordedInput::CursorEntered => write!(f, "cursor_entered"),
+            RecordedInput::CursorLeft => write!(f, "cursor_left"),
+            RecordedInput::MouseWheel { x, y, pixels } => write!(f, "wheel {} {} {}", x, y, pixels),
+            RecordedInput::MouseMotion { x, y } => write!(f, "motion {} {}", x, y),
+            RecordedInput::Modifiers(modifiers) => write!(f, "modifiers {}", modifiers.bits()),
+            RecordedInput::Character(c) => write!(f, "char {}", *c as u32),
+            RecordedInput::Focused(focused) => write!(f, "focused {}", focused),
+            RecordedInput::Resized { width, height } => write!(f, "resized {} {}", width, height),
+            RecordedInput::CloseRequested => write!(f, "close_requested"),
+        }
+    }
+}
+
+impl std::str::FromStr for RecordedInput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split_whitespace();
+        let kind = fields.next().ok_or("empty event")?;
+        let mut next = || fields.next().ok_or_else(|| format!("{} is missing fields", kind));
+
+        Ok(match kind {
+            "key" => RecordedInput::Keyboard {
+                scancode: parse(next()?)?,
+                keycode: match next()? {
+                    "-" => None,
+                    name => Some(key_from_name(name).ok_or_else(|| format!("unknown key {}", name))?),
+                },
+                state: parse_state(next()?)?,
+            },
+            "mouse" => RecordedInput::MouseButton {
+                button: match next()? {
+                    "Left" => MouseButton::Left,
+                    "Right" => MouseButton::Right,
+                    "Middle" => MouseButton::Middle,
+                    other => MouseButton::Other(parse(other)?),
+                },
+                state: parse_state(next()?)?,
+            },
+            "cursor" => RecordedInput::CursorMoved { x: parse(next()?)?, y: parse(next()?)? },
+            "cursor_entered" => RecordedInput::CursorEntered,
+            "cursor_left" => RecordedInput::CursorLeft,
+            "wheel" => RecordedInput::MouseWheel {
+                x: parse(next()?)?,
+                y: parse(next()?)?,
+                pixels: parse(next()?)?,
+            },
+            "motion" => RecordedInput::MouseMotion { x: parse(next()?)?, y: parse(next()?)? },
+            "modifiers" => RecordedInput::Modifiers(ModifiersState::from_bits_truncate(parse(next()?)?)),
+            "char" => RecordedInput::Character(
+                char::from_u32(parse(next()?)?).ok_or("invalid character")?
+            ),
+            "focused" => RecordedInput::Focused(parse(next()?)?),
+            "resized" => RecordedInput::Resized { width: parse(next()?)?, height: parse(next()?)? },
+            "close_requested" => RecordedInput::CloseRequested,
+            _ => return Err(format!("unknown event {}", kind)),
+        })
+    }
+}
+
+fn state_name(state: ElementState) -> &'static str {
+    match state {
+        ElementState::Pressed => "pressed",
+        ElementState::Released => "released",
+    }
+}
+
+fn parse_state(s: &str) -> Result<ElementState, String> {
+    match s {
+        "pressed" => Ok(ElementState::Pressed),
+        "released" => Ok(ElementState::Released),
+        _ => Err(format!("unknown button state {}", s)),
+    }
+}
+
+fn parse<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("invalid value {}", s))
+}
+
+impl Recording {
+    // `frame <index> <delta nanos>` and `event <frame> <time nanos> <event>` lines, # comments
+    pub fn parse(recording: &str) -> Result<Self, RecordingError> {
+        let mut parsed = Recording::default();
+        for (index, line) in recording.lines().enumerate() {
+            let parse_error = |message: String| RecordingError::Parse { line: index + 1, message };
+
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields = line.splitn(4, ' ');
+            match fields.next() {
+                Some("frame") => {
+                    let index: usize = parse(fields.next().unwrap_or("")).map_err(parse_error)?;
+                    if index != parsed.frames.len() {
+                        return Err(parse_error(format!("expected frame {}, got {}", parsed.frames.len(), index)));
+                    }
+                    let nanos: u64 = parse(fields.next().unwrap_or("")).map_err(parse_error)?;
+                    parsed.frames.push(Duration::from_nanos(nanos));
+                },
+                Some("event") => {
+                    let frame = parse(fields.next().unwrap_or("")).map_err(parse_error)?;
+                    // replay walks the events once, front to back
+                    if parsed.events.last().is_some_and(|last| last.frame > frame) {
+                        return Err(parse_error(format!("event for frame {} after events for later frames", frame)));
+                    }
+                    let nanos: u64 = parse(fields.next().unwrap_or("")).map_err(parse_error)?;
+                    let input = fields.next().unwrap_or("").parse().map_err(parse_error)?;
+                    parsed.events.push(RecordedEvent {
+                        frame,
+                        time: Duration::from_nanos(nanos),
+                        input,
+                    });
+                },
+                Some(other) => return Err(parse_error(format!("unknown line {}", other))),
+                None => {},
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RecordingError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    // events are in frame order, so this is a binary search
+    pub fn events_for_frame(&self, frame: u64) -> impl Iterator<Item = &RecordedEvent> {
+        let start = self.events.partition_point(|e| e.frame < frame);
+        self.events[start..].iter().take_while(move |e| e.frame == frame)
+    }
+}
+
+impl fmt::Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# fox-graphics input recording")?;
+        // events come before the frame they were delivered to, same as when recording
+        let mut events = self.events.iter().peekable();
+        for (index, delta) in self.frames.iter().enumerate() {
+            while let Some(event) = events.next_if(|e| e.frame <= index as u64) {
+                writeln!(f, "event {} {} {}", event.frame, event.time.as_nanos(), event.input)?;
+            }
+            writeln!(f, "frame {} {}", index, delta.as_nanos())?;
+        }
+        for event in events {
+            writeln!(f, "event {} {} {}", event.frame, event.time.as_nanos(), event.input)?;
+        }
+        Ok(())
+    }
+}
+
+// ====== recording ======
+
+pub struct InputRecorder {
+    start: Instant,
+    pub recording: Recording,
+    // written when the event loop exits
+    pub path: Option<PathBuf>,
+}
+
+impl InputRecorder {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            recording: Recording::default(),
+            path: None,
+        }
+    }
+
+    pub fn to_file<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::new()
+        }
+    }
+
+    pub fn record(&mut self, frame: u64, input: RecordedInput) {
+        self.recording.events.push(RecordedEvent {
+            frame,
+            time: self.start.elapsed(),
+            input,
+        });
+    }
+
+    pub fn record_window_event(&mut self, frame: u64, event: &WindowEvent) {
+        if let Some(input) = RecordedInput::from_window_event(event) {
+            self.record(frame, input);
+        }
+    }
+
+    pub fn record_device_event(&mut self, frame: u64, event: &DeviceEvent) {
+        if let Some(input) = RecordedInput::from_device_event(event) {
+            self.record(frame, input);
+        }
+    }
+
+    pub fn record_frame(&mut self, frame: u64, delta: Duration) {
+        // the clock can be stepped without recording, keep the frame list dense anyway
+        while (self.recording.frames.len() as u64) < frame {
+            self.recording.frames.push(Duration::ZERO);
+        }
+        self.recording.frames.push(delta);
+    }
+
+    // writes the recording to its path (if it has one) and hands it back
+    pub fn finish(self) -> std::io::Result<Recording> {
+        if let Some(path) = &self.path {
+            self.recording.save(path)?;
+        }
+        Ok(self.recording)
+    }
+}
+
+impl Default for InputRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ====== replay ======
+
+// feeds a recording into the app frame by frame with the recorded frame times, usually on a
+// headless skeleton. stops early (returning the number of frames played) if the app accepts
+// a recorded close request or quit action
+pub fn replay<E: Application>(
+    app: &mut E,
+    skeleton: &mut AppSkeleton,
+    recording: &Recording,
+) -> Result<usize, wgpu::SurfaceError> {
+    let mut events = recording.events.iter().peekable();
+    for (frame, delta) in recording.frames.iter().enumerate() {
+        while let Some(event) = events.next_if(|e| e.frame <= frame as u64) {
+            if let Some(device_event) = event.input.to_device_event() {
+                skeleton.input.process_device_event(&device_event);
+                continue;
+            }
+            let Some(window_event) = event.input.to_window_event() else {
+                continue;
+            };
+
+            skeleton.input.process_window_event(&window_event);
+            if app.input(&window_event) {
+                continue;
+            }
+            let close = match &window_event {
+                // minimizing records a 0x0 resize, run() skips those too
+                WindowEvent::Resized(size) => {
+                    if crate::resize_possible(size) {
+                        crate::resize(app, skeleton, *size, 1.0);
+                    }
+                    false
+                },
+                WindowEvent::CloseRequested => true,
+                event => skeleton.actions.matches_event(QUIT_ACTION, event, &skeleton.input),
+            };
+            if close && app.on_close_requested() {
+                app.on_exit();
+                return Ok(frame);
+            }
+        }
+        crate::step_with_delta(app, skeleton, *delta)?;
+    }
+    Ok(recording.frames.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording() -> Recording {
+        let mut recorder = InputRecorder::new();
+        recorder.record(0, RecordedInput::Resized { width: 640, height: 480 });
+        recorder.record(0, RecordedInput::Keyboard {
+            scancode: 30,
+            keycode: Some(winit::event::VirtualKeyCode::A),
+            state: ElementState::Pressed,
+        });
+        recorder.record_frame(0, Duration::from_millis(16));
+        recorder.record(1, RecordedInput::CursorMoved { x: 10.5, y: -3.0 });
+        recorder.record(1, RecordedInput::MouseButton { button: MouseButton::Other(4), state: ElementState::Released });
+        recorder.record(1, RecordedInput::MouseWheel { x: 0.0, y: 1.5, pixels: false });
+        recorder.record_frame(1, Duration::from_millis(17));
+        // frame 2 was stepped without recording
+        recorder.record(3, RecordedInput::MouseMotion { x: 1.0, y: 2.0 });
+        recorder.record(3, RecordedInput::Modifiers(ModifiersState::CTRL | ModifiersState::SHIFT));
+        recorder.record(3, RecordedInput::Character('ß'));
+        recorder.record(3, RecordedInput::Focused(false));
+        recorder.record_frame(3, Duration::from_millis(15));
+        recorder.record(4, RecordedInput::Resized { width: 0, height: 0 });
+        recorder.record(4, RecordedInput::CloseRequested);
+        recorder.finish().unwrap()
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let recording = recording();
+        let parsed = Recording::parse(&recording.to_string()).unwrap();
+        assert_eq!(parsed, recording);
+    }
+
+    #[test]
+    fn frames_and_events_line_up() {
+        let recording = recording();
+        assert_eq!(recording.frames, [16, 17, 0, 15].map(Duration::from_millis));
+        let frames: Vec<usize> = (0..5).map(|frame| recording.events_for_frame(frame).count()).collect();
+        assert_eq!(frames, [2, 3, 0, 4, 2]);
+
+        // events still come right before the frame they were delivered to
+        let text = recording.to_string();
+        let lines: Vec<&str> = text.lines().filter(|l| !l.starts_with('#')).collect();
+        assert!(lines[0].starts_with("event 0 "));
+        assert_eq!(lines[2], "frame 0 16000000");
+        assert_eq!(lines[6], "frame 1 17000000");
+        assert_eq!(lines[7], "frame 2 0");
+        assert_eq!(lines[12], "frame 3 15000000");
+        assert!(lines[13].starts_with("event 4 "));
+    }
+
+    #[test]
+    fn out_of_order_events_are_rejected() {
+        let error = Recording::parse("event 1 0 focused true\nevent 0 0 focused false\n").unwrap_err();
+        assert!(matches!(error, RecordingError::Parse { line: 2, .. }));
+    }
+}
@@ -0,0 +1,282 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use crate::error::ShaderError;
+use crate::preprocessor::{Defines, PreprocessedShader, ShaderLibrary, SourceLine};
+
+// ====== shader files ======
+
+// a wgsl file plus what it gets preprocessed with, From a path uses the default library and no
+// defines (PipelineBuilder swaps in the skeleton's library and its own defines)
+#[derive(Clone, Debug)]
+pub struct ShaderFile {
+    pub path: PathBuf,
+    // None until someone picks a library, Shader::from_file falls back to ShaderLibrary::new
+    pub library: Option<Rc<ShaderLibrary>>,
+    pub defines: Defines,
+}
+
+impl ShaderFile {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            library: None,
+            defines: Defines::new(),
+        }
+    }
+
+    pub fn with_library(mut self, library: Rc<ShaderLibrary>) -> Self {
+        self.library = Some(library);
+        self
+    }
+
+    // same as #define NAME value at the top of the file, use "" for a plain #ifdef flag
+    pub fn with_define(mut self, name: &str, value: &str) -> Self {
+        self.defines.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn preprocess(&self) -> Result<PreprocessedShader, ShaderError> {
+        match &self.library {
+            Some(library) => library.preprocess_file(&self.path, &self.defines),
+            None => ShaderLibrary::new().preprocess_file(&self.path, &self.defines),
+        }
+    }
+}
+
+impl From<&str> for ShaderFile {
+    fn from(path: &str) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<String> for ShaderFile {
+    fn from(path: String) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<&Path> for ShaderFile {
+    fn from(path: &Path) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<&PathBuf> for ShaderFile {
+    fn from(path: &PathBuf) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<PathBuf> for ShaderFile {
+    fn from(path: PathBuf) -> Self {
+        Self::new(path)
+    }
+}
+
+// ====== shader modules ======
+
+// a shader module plus what naga could tell us about it, so pipelines can check their entry
+// points before handing them to wgpu
+pub struct Shader {
+    pub module: wgpu::ShaderModule,
+    // only wgsl sources get reflected, None for everything else
+    pub reflection: Option<naga::Module>,
+    // naga's validation of the reflected module (which globals each entry point uses), None if
+    // it wasn't reflected or doesn't validate
+    pub module_info: Option<naga::valid::ModuleInfo>,
+    // set for shaders loaded with from_file, so they can be hot reloaded
+    pub file: Option<ShaderFile>,
+    // every file on disk that went into the module (the main file and its includes) with the
+    // modification time we compiled (or tried to compile), a RefCell so a failed reload can
+    // bump them without replacing the shader
+    pub watched: RefCell<Vec<(PathBuf, Option<SystemTime>)>>,
+}
+
+impl Shader {
+    pub fn new(device: &wgpu::Device, label: &str, source: wgpu::ShaderSource) -> Self {
+        let reflection = match &source {
+            wgpu::ShaderSource::Wgsl(code) => match naga::front::wgsl::parse_str(code) {
+                Ok(module) => Some(module),
+                // wgpu reports the same error (with nicer formatting) when it compiles the module
+                Err(e) => {
+                    log::error!("failed to reflect {}: {}", label, e.emit_to_string(code));
+                    None
+                },
+            },
+            _ => None,
+        };
+        // for layout_from_reflection, wgpu reports the actual validation errors below
+        let module_info = reflection.as_ref().and_then(|module| validator().validate(module).ok());
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source,
+        });
+        Self {
+            module,
+            reflection,
+            module_info,
+            file: None,
+            watched: RefCell::new(Vec::new()),
+        }
+    }
+
+    // checks the preprocessed code with naga first, nothing reaches wgpu if it's broken
+    pub fn from_preprocessed(device: &wgpu::Device, label: &str, shader: &PreprocessedShader) -> Result<Self, ShaderError> {
+        let (reflection, module_info) = shader.validate(label)?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(shader.code.as_str().into()),
+        });
+        Ok(Self {
+            module,
+            reflection: Some(reflection),
+            module_info: Some(module_info),
+            file: None,
+            watched: RefCell::new(Vec::new()),
+        })
+    }
+
+    // loads, preprocesses and checks a wgsl file, a broken file can't take the app down
+    pub fn from_file<F: Into<ShaderFile>>(device: &wgpu::Device, file: F) -> Result<Self, ShaderError> {
+        let file = file.into();
+        // before reading, so a save during the load still counts as a change
+        let mut watched = vec![(file.path.clone(), modified_time(&file.path))];
+        let preprocessed = file.preprocess()?;
+        watched.extend(preprocessed.files.iter().skip(1).map(|path| (path.clone(), modified_time(path))));
+        let mut shader = Self::from_preprocessed(device, &file.path.display().to_string(), &preprocessed)?;
+        shader.file = Some(file);
+        shader.watched = RefCell::new(watched);
+        Ok(shader)
+    }
+
+    // true if the file or anything it includes has been modified since it was last (re)loaded
+    pub fn changed_on_disk(&self) -> bool {
+        self.watched.borrow().iter().any(|(path, modified)| modified_time(path) != *modified)
+    }
+
+    // changed_on_disk is false again until the next change, even if reloading fails
+    pub fn mark_seen(&self) {
+        for (path, modified) in self.watched.borrow_mut().iter_mut() {
+            *modified = modified_time(path);
+        }
+    }
+
+    // None for shaders that didn't come from a file
+    pub fn reload(&self, device: &wgpu::Device) -> Option<Result<Self, ShaderError>> {
+        self.file.clone().map(|file| Self::from_file(device, file))
+    }
+
+    pub fn from_wgsl(device: &wgpu::Device, label: &str, code: &str) -> Self {
+        Self::new(device, label, wgpu::ShaderSource::Wgsl(code.into()))
+    }
+
+    // None if the module wasn't reflected
+    pub fn entry_points(&self) -> Option<Vec<(&str, naga::ShaderStage)>> {
+        self.reflection.as_ref().map(|module| {
+            module.entry_points.iter()
+                .map(|entry_point| (entry_point.name.as_str(), entry_point.stage))
+                .collect()
+        })
+    }
+
+    // unreflected modules are given the benefit of the doubt
+    pub fn has_entry_point(&self, name: &str, stage: naga::ShaderStage) -> bool {
+        self.entry_points().is_none_or(|entry_points| entry_points.contains(&(name, stage)))
+    }
+}
+
+// the files behind a pipeline's shaders with the modification times it last saw. every pipeline
+// keeps its own, otherwise a shader shared between pipelines (with_shared_shader) would only get
+// reloaded for the first one to notice the change
+#[derive(Clone, Debug, Default)]
+pub struct ShaderWatch {
+    files: RefCell<Vec<(PathBuf, Option<SystemTime>)>>,
+}
+
+impl ShaderWatch {
+    // starts out with the times the shaders were compiled from
+    pub fn new<'s, I: IntoIterator<Item = &'s Shader>>(shaders: I) -> Self {
+        let files = shaders.into_iter()
+            .flat_map(|shader| shader.watched.borrow().clone())
+            .collect();
+        Self {
+            files: RefCell::new(files),
+        }
+    }
+
+    pub fn changed_on_disk(&self) -> bool {
+        self.files.borrow().iter().any(|(path, modified)| modified_time(path) != *modified)
+    }
+
+    pub fn mark_seen(&self) {
+        for (path, modified) in self.files.borrow_mut().iter_mut() {
+            *modified = modified_time(path);
+        }
+    }
+}
+
+// parses and validates wgsl with naga, the error is naga's full diagnostic
+pub fn check_wgsl(code: &str, path: &str) -> Result<naga::Module, ShaderError> {
+    check_wgsl_labeled(code, path, path, |_| None).map(|(module, _)| module)
+}
+
+// check_wgsl for preprocessed code, source_line maps a line of code back to where it was written
+pub(crate) fn check_wgsl_at<F: Fn(u32) -> Option<SourceLine>>(code: &str, path: &str, source_line: F) -> Result<(naga::Module, naga::valid::ModuleInfo), ShaderError> {
+    // the snippet's line numbers are the preprocessed ones, don't pass them off as the file's
+    check_wgsl_labeled(code, path, &format!("{} (preprocessed)", path), source_line)
+}
+
+fn check_wgsl_labeled<F: Fn(u32) -> Option<SourceLine>>(code: &str, path: &str, label: &str, source_line: F) -> Result<(naga::Module, naga::valid::ModuleInfo), ShaderError> {
+    // naga's snippet shows the preprocessed code, so point at the original line above it
+    let diagnostic = |location: Option<naga::SourceLocation>, emitted: String| {
+        match location.and_then(|location| Some((source_line(location.line_number)?, location))) {
+            Some((line, location)) => format!("at {}:{}:{}\n{}", line.file, line.line, location.line_position, emitted),
+            None => emitted,
+        }
+    };
+    let module = naga::front::wgsl::parse_str(code).map_err(|e| ShaderError::Compile {
+        path: path.to_string(),
+        diagnostic: diagnostic(e.location(code), e.emit_to_string_with_path(code, label)),
+    })?;
+    let info = validator().validate(&module).map_err(|e| ShaderError::Compile {
+        path: path.to_string(),
+        diagnostic: diagnostic(e.location(code), e.emit_to_string_with_path(code, label)),
+    })?;
+    Ok((module, info))
+}
+
+// capabilities are wgpu's business, it checks them against the device when compiling
+fn validator() -> naga::valid::Validator {
+    naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watches_are_independent() {
+        let path = std::env::temp_dir().join(format!("shader_watch_{}.wgsl", std::process::id()));
+        std::fs::write(&path, "// a").unwrap();
+        let files = vec![(path.clone(), modified_time(&path))];
+        let first = ShaderWatch { files: RefCell::new(files.clone()) };
+        let second = ShaderWatch { files: RefCell::new(files) };
+
+        // make sure the modification time actually moves
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(&path, "// b").unwrap();
+        assert!(first.changed_on_disk());
+        first.mark_seen();
+        assert!(!first.changed_on_disk());
+        // seeing the change in one pipeline doesn't hide it from the other
+        assert!(second.changed_on_disk());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
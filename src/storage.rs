@@ -0,0 +1,200 @@
+use crate::app::AppSkeleton;
+
+use wgpu::util::DeviceExt;
+
+// storage buffers can also be drawn from directly and copied in and out
+pub const STORAGE_BUFFER_USAGE: wgpu::BufferUsages = wgpu::BufferUsages::STORAGE
+    .union(wgpu::BufferUsages::VERTEX)
+    .union(wgpu::BufferUsages::COPY_SRC)
+    .union(wgpu::BufferUsages::COPY_DST);
+
+// binding 0 is read, binding 1 is written, matches PingPong's bind groups:
+//   @group(n) @binding(0) var<storage, read> src: array<T>;
+//   @group(n) @binding(1) var<storage, read_write> dst: array<T>;
+pub const PING_PONG_LAYOUT_ENTRIES: &[wgpu::BindGroupLayoutEntry] = &[
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    wgpu::BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+];
+
+// a storage buffer of len elements, stride bytes each. the wgpu buffer itself is padded to a
+// multiple of 4 bytes (COPY_BUFFER_ALIGNMENT) so it can always be copied out
+pub struct StorageBuffer {
+    pub buffer: wgpu::Buffer,
+    pub len: u64,
+    pub stride: u64,
+}
+
+impl StorageBuffer {
+    pub fn new<T: bytemuck::Pod>(device: &wgpu::Device, label: &str, contents: &[T]) -> Self {
+        assert!(std::mem::size_of_val(contents) > 0, "cannot create storage buffer {} without contents!", label);
+        // pads to COPY_BUFFER_ALIGNMENT by itself
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(contents),
+            usage: STORAGE_BUFFER_USAGE,
+        });
+        Self {
+            buffer,
+            len: contents.len() as u64,
+            stride: std::mem::size_of::<T>() as u64,
+        }
+    }
+
+    // wgpu zeroes new buffers
+    pub fn zeroed<T: bytemuck::Pod>(device: &wgpu::Device, label: &str, len: u64) -> Self {
+        let stride = std::mem::size_of::<T>() as u64;
+        assert!(len * stride > 0, "cannot create empty storage buffer {}!", label);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: (len * stride).next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+            usage: STORAGE_BUFFER_USAGE,
+            mapped_at_creation: false,
+        });
+        Self {
+            buffer,
+            len,
+            stride,
+        }
+    }
+
+    pub fn size(&self) -> wgpu::BufferAddress {
+        self.len * self.stride
+    }
+
+    // overwrites elements starting at first. the byte offset and size have to be multiples of 4
+    // (COPY_BUFFER_ALIGNMENT), which only matters for elements smaller than that
+    pub fn write<T: bytemuck::Pod>(&self, queue: &wgpu::Queue, first: u64, data: &[T]) {
+        assert!(first + data.len() as u64 <= self.len, "cannot write {} elements at {} into storage buffer of {}!", data.len(), first, self.len);
+        let offset = first * self.stride;
+        let size = std::mem::size_of_val(data) as u64;
+        assert!(
+            offset % wgpu::COPY_BUFFER_ALIGNMENT == 0 && size % wgpu::COPY_BUFFER_ALIGNMENT == 0,
+            "cannot write {} bytes at byte {} into storage buffer, both have to be multiples of {}!", size, offset, wgpu::COPY_BUFFER_ALIGNMENT,
+        );
+        queue.write_buffer(&self.buffer, offset, bytemuck::cast_slice(data));
+    }
+
+    // blocks until the gpu is done, meant for tests and tools rather than every frame
+    pub fn read<T: bytemuck::Pod>(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<T> {
+        // the whole padded buffer, copies have to be multiples of 4 bytes
+        let padded_size = self.buffer.size();
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Storage Readback Buffer"),
+            size: padded_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Storage Readback Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(&self.buffer, 0, &readback, 0, padded_size);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("failed to map storage readback buffer!");
+        });
+        device.poll(wgpu::Maintain::Wait);
+
+        // copied into a Vec<T> so the mapped bytes don't have to be aligned for T
+        let mut data = vec![T::zeroed(); self.len as usize];
+        bytemuck::cast_slice_mut(&mut data).copy_from_slice(&slice.get_mapped_range()[..self.size() as usize]);
+        readback.unmap();
+        data
+    }
+}
+
+// two storage buffers for passes that read last step's results and write the next ones
+// (particle simulation, blurs, ...). bind_group() reads current() and writes next(), swap()
+// after each dispatch
+pub struct PingPong {
+    pub buffers: [StorageBuffer; 2],
+    pub layout: wgpu::BindGroupLayout,
+    // bind_groups[i] reads buffers[i] and writes the other one
+    pub bind_groups: [wgpu::BindGroup; 2],
+    current: usize,
+}
+
+impl PingPong {
+    // both buffers start out with contents, which can't be empty
+    pub fn new<T: bytemuck::Pod>(device: &wgpu::Device, label: &str, contents: &[T]) -> Self {
+        let buffers = [
+            StorageBuffer::new(device, &format!("{} A", label), contents),
+            StorageBuffer::new(device, &format!("{} B", label), contents),
+        ];
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(format!("{} Bind Group Layout", label).as_str()),
+            entries: PING_PONG_LAYOUT_ENTRIES,
+        });
+        let bind_group = |src: &StorageBuffer, dst: &StorageBuffer, name: &str| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(format!("{} Bind Group {}", label, name).as_str()),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: src.buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: dst.buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let bind_groups = [
+            bind_group(&buffers[0], &buffers[1], "A to B"),
+            bind_group(&buffers[1], &buffers[0], "B to A"),
+        ];
+        Self {
+            buffers,
+            layout,
+            bind_groups,
+            current: 0,
+        }
+    }
+
+    // what the next pass reads, after swap() the results of the last one
+    pub fn current(&self) -> &StorageBuffer {
+        &self.buffers[self.current]
+    }
+
+    // what the next pass writes
+    pub fn next(&self) -> &StorageBuffer {
+        &self.buffers[1 - self.current]
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_groups[self.current]
+    }
+
+    pub fn swap(&mut self) {
+        self.current = 1 - self.current;
+    }
+}
+
+impl AppSkeleton {
+    pub fn create_storage_buffer<T: bytemuck::Pod>(&self, label: &str, contents: &[T]) -> StorageBuffer {
+        StorageBuffer::new(&self.device, label, contents)
+    }
+
+    pub fn create_ping_pong<T: bytemuck::Pod>(&self, label: &str, contents: &[T]) -> PingPong {
+        PingPong::new(&self.device, label, contents)
+    }
+}
@@ -0,0 +1,81 @@
+// ====== surface presentation preferences ======
+
+// every list is in order of preference, the first entry the surface supports wins. empty lists
+// (or lists with nothing supported) fall back to what the surface reports first
+#[derive(Clone, Debug)]
+pub struct SurfacePreferences {
+    pub present_modes: Vec<wgpu::PresentMode>,
+    pub alpha_modes: Vec<wgpu::CompositeAlphaMode>,
+    pub formats: Vec<wgpu::TextureFormat>,
+}
+
+impl Default for SurfacePreferences {
+    fn default() -> Self {
+        Self {
+            // attempt to use Mailbox present_mode, otherwise fallback to Fifo (VSync)
+            present_modes: vec![wgpu::PresentMode::Mailbox, wgpu::PresentMode::Fifo],
+            alpha_modes: Vec::new(),
+            formats: Vec::new(),
+        }
+    }
+}
+
+impl SurfacePreferences {
+    pub fn vsync() -> Self {
+        Self::default().with_present_modes(&[wgpu::PresentMode::Fifo])
+    }
+
+    pub fn low_latency() -> Self {
+        Self::default().with_present_modes(&[
+            wgpu::PresentMode::Mailbox,
+            wgpu::PresentMode::Immediate,
+            wgpu::PresentMode::Fifo,
+        ])
+    }
+
+    // linear float and 10 bit formats, for when the display can actually show them
+    pub fn hdr() -> Self {
+        Self::default().with_formats(&[
+            wgpu::TextureFormat::Rgba16Float,
+            wgpu::TextureFormat::Rgb10a2Unorm,
+        ])
+    }
+
+    pub fn with_present_modes(mut self, present_modes: &[wgpu::PresentMode]) -> Self {
+        self.present_modes = present_modes.to_vec();
+        self
+    }
+
+    pub fn with_alpha_modes(mut self, alpha_modes: &[wgpu::CompositeAlphaMode]) -> Self {
+        self.alpha_modes = alpha_modes.to_vec();
+        self
+    }
+
+    pub fn with_formats(mut self, formats: &[wgpu::TextureFormat]) -> Self {
+        self.formats = formats.to_vec();
+        self
+    }
+
+    pub fn choose_format(&self, capabilities: &wgpu::SurfaceCapabilities) -> wgpu::TextureFormat {
+        first_supported(&self.formats, &capabilities.formats)
+            .or_else(|| capabilities.formats.iter().copied().find(|f| f.is_srgb()))
+            .unwrap_or(capabilities.formats[0])
+    }
+
+    pub fn choose_present_mode(&self, capabilities: &wgpu::SurfaceCapabilities) -> wgpu::PresentMode {
+        // Fifo is the only mode every surface has to support
+        first_supported(&self.present_modes, &capabilities.present_modes)
+            .unwrap_or(wgpu::PresentMode::Fifo)
+    }
+
+    pub fn choose_alpha_mode(&self, capabilities: &wgpu::SurfaceCapabilities) -> wgpu::CompositeAlphaMode {
+        first_supported(&self.alpha_modes, &capabilities.alpha_modes)
+            .unwrap_or(capabilities.alpha_modes[0])
+    }
+}
+
+fn first_supported<T: Copy + PartialEq>(preferred: &[T], supported: &[T]) -> Option<T> {
+    preferred.iter()
+        .copied()
+        .find(|p| supported.contains(p))
+}
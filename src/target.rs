@@ -0,0 +1,334 @@
+// ====== render targets ======
+
+// an offscreen color texture standing in for a swapchain when there is no window
+pub struct OffscreenTexture {
+    pub texture: wgpu::Texture,
+    pub size: wgpu::Extent3d,
+    pub format: wgpu::TextureFormat,
+}
+
+impl OffscreenTexture {
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        let size = wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Color Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        Self {
+            texture,
+            size,
+            format: config.format,
+        }
+    }
+
+    // copies the texture back to the cpu, rows are tightly packed (no wgpu row padding)
+    pub fn read_pixels(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<u8> {
+        let bytes_per_pixel = self.format.block_size(None)
+            .expect("cannot read back offscreen texture: format has no block size!");
+        let unpadded_bytes_per_row = self.size.width * bytes_per_pixel;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Offscreen Readback Buffer"),
+            size: (padded_bytes_per_row * self.size.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Offscreen Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(self.size.height),
+                },
+            },
+            self.size,
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("failed to map offscreen readback buffer!");
+        });
+        device.poll(wgpu::Maintain::Wait);
+
+        let padded = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * self.size.height) as usize);
+        for row in padded.chunks(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+        drop(padded);
+        buffer.unmap();
+        pixels
+    }
+}
+
+// a depth (or depth-stencil) texture matching a render target's size
+pub struct DepthTexture {
+    pub texture: wgpu::Texture,
+    pub format: wgpu::TextureFormat,
+}
+
+impl DepthTexture {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: depth_usage(sample_count),
+            view_formats: &[],
+        });
+        Self { texture, format }
+    }
+}
+
+// the multisampled color texture frames are drawn into before resolving to the output
+pub fn create_msaa_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Multisampled Color Texture"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    })
+}
+
+// what the device can actually do with the format, counts other than 1 and 4 also need
+// Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+pub fn sample_count_supported(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> bool {
+    let features = if device.features().contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+        adapter.get_texture_format_features(format)
+    } else {
+        format.guaranteed_format_features(device.features())
+    };
+    sample_count == 1 || features.flags.sample_count_supported(sample_count)
+}
+
+fn depth_usage(sample_count: u32) -> wgpu::TextureUsages {
+    if sample_count > 1 {
+        // multisampled depth can't be sampled like a normal texture anyway, and on gl the binding
+        // usage turns it into a texture that won't share a framebuffer with the msaa color
+        wgpu::TextureUsages::RENDER_ATTACHMENT
+    } else {
+        // binding too so it can be sampled afterwards (shadow maps, ssao...)
+        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+    }
+}
+
+pub enum TargetOutput {
+    Surface(wgpu::Surface),
+    Offscreen(OffscreenTexture),
+}
+
+// where frames end up, plus the attachments that have to follow its size around
+pub struct RenderTarget {
+    pub output: TargetOutput,
+    pub depth: Option<DepthTexture>,
+    // 1 means no msaa, pipelines drawing into this target need the same count
+    pub sample_count: u32,
+    pub msaa: Option<wgpu::Texture>,
+}
+
+impl RenderTarget {
+    pub fn from_surface(surface: wgpu::Surface) -> Self {
+        Self {
+            output: TargetOutput::Surface(surface),
+            depth: None,
+            sample_count: 1,
+            msaa: None,
+        }
+    }
+
+    pub fn from_offscreen(texture: OffscreenTexture) -> Self {
+        Self {
+            output: TargetOutput::Offscreen(texture),
+            depth: None,
+            sample_count: 1,
+            msaa: None,
+        }
+    }
+
+    // adds (or with None, removes) a depth texture, it gets recreated by configure()
+    pub fn set_depth_format(
+        &mut self,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        format: Option<wgpu::TextureFormat>,
+    ) {
+        self.depth = format.map(|format| DepthTexture::new(device, config, format, self.sample_count));
+    }
+
+    // recreates the msaa color and depth textures, check sample_count_supported() first
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) {
+        self.sample_count = sample_count;
+        self.msaa = (sample_count > 1).then(|| create_msaa_texture(device, config, sample_count));
+        let depth_format = self.depth_format();
+        self.set_depth_format(device, config, depth_format);
+    }
+
+    pub fn depth_format(&self) -> Option<wgpu::TextureFormat> {
+        self.depth.as_ref().map(|depth| depth.format)
+    }
+
+    pub fn configure(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        match &mut self.output {
+            TargetOutput::Surface(surface) => surface.configure(device, config),
+            // offscreen textures cant be resized, just make a new one
+            TargetOutput::Offscreen(texture) => *texture = OffscreenTexture::new(device, config),
+        }
+        if let Some(depth) = &mut self.depth {
+            *depth = DepthTexture::new(device, config, depth.format, self.sample_count);
+        }
+        if let Some(msaa) = &mut self.msaa {
+            *msaa = create_msaa_texture(device, config, self.sample_count);
+        }
+    }
+
+    pub fn current_frame(&self) -> Result<Frame, wgpu::SurfaceError> {
+        let depth_view = self.depth.as_ref()
+            .map(|depth| depth.texture.create_view(&wgpu::TextureViewDescriptor::default()));
+        let msaa_view = self.msaa.as_ref()
+            .map(|msaa| msaa.create_view(&wgpu::TextureViewDescriptor::default()));
+        match &self.output {
+            TargetOutput::Surface(surface) => {
+                let surface_texture = surface.get_current_texture()?;
+                let view = surface_texture.texture.create_view(&wgpu::TextureViewDescriptor::default());
+                Ok(Frame {
+                    view,
+                    msaa_view,
+                    depth_view,
+                    depth_format: self.depth_format(),
+                    surface_texture: Some(surface_texture),
+                })
+            },
+            TargetOutput::Offscreen(texture) => Ok(Frame {
+                view: texture.texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                msaa_view,
+                depth_view,
+                depth_format: self.depth_format(),
+                surface_texture: None,
+            }),
+        }
+    }
+
+    pub fn surface(&self) -> Option<&wgpu::Surface> {
+        match &self.output {
+            TargetOutput::Surface(surface) => Some(surface),
+            TargetOutput::Offscreen(_) => None,
+        }
+    }
+
+    pub fn offscreen(&self) -> Option<&OffscreenTexture> {
+        match &self.output {
+            TargetOutput::Surface(_) => None,
+            TargetOutput::Offscreen(texture) => Some(texture),
+        }
+    }
+}
+
+// a single frame to draw into, call present() once all work has been submitted
+pub struct Frame {
+    // the output itself, with msaa this is only the resolve target (see color_attachment)
+    pub view: wgpu::TextureView,
+    pub msaa_view: Option<wgpu::TextureView>,
+    pub depth_view: Option<wgpu::TextureView>,
+    depth_format: Option<wgpu::TextureFormat>,
+    surface_texture: Option<wgpu::SurfaceTexture>,
+}
+
+impl Frame {
+    // the color attachment for a render pass, cleared to clear_color or loaded when None. with
+    // msaa it draws into the multisampled texture and resolves into the output
+    pub fn color_attachment(&self, clear_color: Option<wgpu::Color>) -> wgpu::RenderPassColorAttachment<'_> {
+        let load = match clear_color {
+            Some(color) => wgpu::LoadOp::Clear(color),
+            None => wgpu::LoadOp::Load,
+        };
+        match &self.msaa_view {
+            Some(msaa_view) => wgpu::RenderPassColorAttachment {
+                view: msaa_view,
+                resolve_target: Some(&self.view),
+                ops: wgpu::Operations { load, store: true },
+            },
+            None => wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: wgpu::Operations { load, store: true },
+            },
+        }
+    }
+
+    // the depth attachment for a render pass, cleared to clear_depth (usually 1.0) or loaded
+    // when None. stencil is cleared to 0 on formats that have it
+    pub fn depth_attachment(&self, clear_depth: Option<f32>) -> Option<wgpu::RenderPassDepthStencilAttachment<'_>> {
+        let view = self.depth_view.as_ref()?;
+        let load = match clear_depth {
+            Some(depth) => wgpu::LoadOp::Clear(depth),
+            None => wgpu::LoadOp::Load,
+        };
+        let stencil_load = match clear_depth {
+            Some(_) => wgpu::LoadOp::Clear(0),
+            None => wgpu::LoadOp::Load,
+        };
+        Some(wgpu::RenderPassDepthStencilAttachment {
+            view,
+            depth_ops: Some(wgpu::Operations { load, store: true }),
+            stencil_ops: self.depth_format.filter(|f| f.has_stencil_aspect())
+                .map(|_| wgpu::Operations { load: stencil_load, store: true }),
+        })
+    }
+
+    pub fn present(self) {
+        if let Some(surface_texture) = self.surface_texture {
+            surface_texture.present();
+        }
+    }
+}
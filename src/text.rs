@@ -1,4 +1,62 @@
-use crate::app::{AppBuilder, AppSkeleton, Application};
+use crate::app::{AppBuilder, AppSkeleton, Application, SizeDependent};
+use crate::data::ScreenSize;
+
+// screen size, text metadata and the glyph instances, used by add_text2d
+pub const TEXT2D_LAYOUT_ENTRIES: &[wgpu::BindGroupLayoutEntry] = &[
+    wgpu::BindGroupLayoutEntry { // screen metadata
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    wgpu::BindGroupLayoutEntry { // text metadata
+        binding: 1,
+        visibility: wgpu::ShaderStages::VERTEX,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    wgpu::BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::VERTEX,
+        ty: wgpu::BindingType::Buffer  {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None
+        },
+        count: None,
+    }
+];
+
+// atlas texture and its sampler, used by create_glyph_atlas
+pub const GLYPH_ATLAS_LAYOUT_ENTRIES: &[wgpu::BindGroupLayoutEntry] = &[
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            //sample_type: wgpu::TextureSampleType::Uint,
+        },
+        count: None,
+    },
+    wgpu::BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        // This should match the filterable field of the
+        // corresponding Texture entry above.
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+];
 
 pub struct GlyphAtlas {
     pub glyph_bind_group_layout: wgpu::BindGroupLayout,
@@ -8,11 +66,18 @@ pub struct GlyphAtlas {
 pub struct Text2D {
     pub vertex_buffer: wgpu::Buffer,
     pub bind_group_layout: wgpu::BindGroupLayout,
-    //pub bind_group: wgpu::BindGroup,
+    // made by the app once the text buffers exist, needed to auto bind BindSlot::Text2D
+    pub bind_group: Option<wgpu::BindGroup>,
     pub glyph_atlas: GlyphAtlas,
     pub screen_uniform_buffer: wgpu::Buffer,
 }
 
+impl SizeDependent for Text2D {
+    fn resize(&mut self, screen_size: &ScreenSize, _device: &wgpu::Device, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.screen_uniform_buffer, 0, bytemuck::cast_slice(screen_size.size()));
+    }
+}
+
 impl AppSkeleton {
     pub fn create_glyph_atlas(&self, path: &str) -> GlyphAtlas {
         let atlas_bytes: Vec<u8> = std::fs::read(path)
@@ -68,27 +133,7 @@ impl AppSkeleton {
 
         let glyph_bind_group_layout = self.device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            //sample_type: wgpu::TextureSampleType::Uint,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        // This should match the filterable field of the
-                        // corresponding Texture entry above.
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
+                entries: GLYPH_ATLAS_LAYOUT_ENTRIES,
                 label: Some("Glyph Atlas Bind Group Layout"),
             }
         );
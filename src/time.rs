@@ -0,0 +1,148 @@
+use std::time::{Duration, Instant};
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FrameTime {
+    pub delta: Duration,
+    pub elapsed: Duration,
+    pub frame: u64,
+    // how many fixed_update calls ran before this frame
+    pub fixed_steps: u32,
+    // how far we are between the last fixed_update and the next one (0..1), use it to
+    // interpolate simulation state in render. always 0 without a fixed timestep
+    pub alpha: f32,
+}
+
+impl FrameTime {
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+}
+
+pub struct FrameClock {
+    last: Instant,
+    elapsed: Duration,
+    frame: u64,
+    pub fixed_timestep: Option<Duration>,
+    // caps fixed_update calls per frame so a long hitch doesn't snowball into longer frames
+    pub max_fixed_steps: u32,
+    // the longest delta the first frame after an idle wait (RunMode::Reactive) reports, time
+    // spent asleep isn't time the app was running
+    pub max_wake_delta: Duration,
+    accumulator: Duration,
+    waking: bool,
+}
+
+impl FrameClock {
+    pub fn new(fixed_timestep: Option<Duration>) -> Self {
+        Self {
+            last: Instant::now(),
+            elapsed: Duration::ZERO,
+            frame: 0,
+            fixed_timestep,
+            max_fixed_steps: 8,
+            max_wake_delta: Duration::from_millis(33),
+            accumulator: Duration::ZERO,
+            waking: false,
+        }
+    }
+
+    // forget the time spent since the last tick (setup, loading, etc.)
+    pub fn reset(&mut self) {
+        self.last = Instant::now();
+    }
+
+    // the event loop woke up from a wait, the next tick's delta gets capped to max_wake_delta
+    pub fn wake(&mut self) {
+        self.waking = true;
+    }
+
+    // index of the next frame
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn tick(&mut self) -> FrameTime {
+        let now = Instant::now();
+        let mut delta = now - self.last;
+        self.last = now;
+        if std::mem::take(&mut self.waking) {
+            delta = delta.min(self.max_wake_delta);
+        }
+        self.advance(delta)
+    }
+
+    // advances by an exact delta instead of wall clock time, for deterministic stepping
+    pub fn advance(&mut self, delta: Duration) -> FrameTime {
+        self.elapsed += delta;
+        let frame = self.frame;
+        self.frame += 1;
+
+        let (fixed_steps, alpha) = match self.fixed_timestep {
+            Some(timestep) if !timestep.is_zero() => {
+                self.accumulator += delta;
+                let mut steps = 0;
+                while self.accumulator >= timestep && steps < self.max_fixed_steps {
+                    self.accumulator -= timestep;
+                    steps += 1;
+                }
+                // dropped steps are gone for good, otherwise alpha ends up > 1
+                if self.accumulator >= timestep {
+                    self.accumulator = Duration::ZERO;
+                }
+                (steps, self.accumulator.as_secs_f32() / timestep.as_secs_f32())
+            },
+            _ => (0, 0.0),
+        };
+
+        FrameTime {
+            delta,
+            elapsed: self.elapsed,
+            frame,
+            fixed_steps,
+            alpha,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_steps_accumulate() {
+        let mut clock = FrameClock::new(Some(Duration::from_millis(10)));
+        let time = clock.advance(Duration::from_millis(25));
+        assert_eq!(time.fixed_steps, 2);
+        assert!((time.alpha - 0.5).abs() < 1e-4);
+        let time = clock.advance(Duration::from_millis(5));
+        assert_eq!(time.fixed_steps, 1);
+        assert_eq!(time.frame, 1);
+        assert_eq!(time.elapsed, Duration::from_millis(30));
+    }
+
+    #[test]
+    fn fixed_steps_are_capped() {
+        let mut clock = FrameClock::new(Some(Duration::from_millis(10)));
+        let time = clock.advance(Duration::from_secs(1));
+        assert_eq!(time.fixed_steps, clock.max_fixed_steps);
+        assert_eq!(time.alpha, 0.0);
+    }
+
+    #[test]
+    fn waking_caps_delta() {
+        let mut clock = FrameClock::new(Some(Duration::from_millis(10)));
+        clock.last = Instant::now() - Duration::from_secs(5);
+        clock.wake();
+        let time = clock.tick();
+        assert_eq!(time.delta, clock.max_wake_delta);
+        assert!(time.fixed_steps <= 3);
+
+        // only the first tick after waking
+        clock.last = Instant::now() - Duration::from_secs(5);
+        assert!(clock.tick().delta >= Duration::from_secs(5));
+    }
+}
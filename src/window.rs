@@ -0,0 +1,283 @@
+use crate::app::AppSkeleton;
+use crate::error::GraphicsInitError;
+use crate::target::RenderTarget;
+
+use winit::{
+    dpi::{Position, Size},
+    event_loop::EventLoopWindowTarget,
+    monitor::{MonitorHandle, VideoMode},
+    window::{Fullscreen, Icon, Window, WindowBuilder, WindowId},
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FullscreenMode {
+    Borderless,
+    // switches the monitor to its largest, fastest video mode
+    Exclusive,
+}
+
+#[derive(Clone, Debug)]
+pub struct WindowIcon {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl WindowIcon {
+    pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Self {
+        Self { rgba, width, height }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WindowOptions {
+    pub title: String,
+    pub inner_size: Option<Size>,
+    pub min_inner_size: Option<Size>,
+    pub max_inner_size: Option<Size>,
+    pub position: Option<Position>,
+    pub fullscreen: Option<FullscreenMode>,
+    pub resizable: bool,
+    pub decorations: bool,
+    // only makes the window transparent, the surface also needs a non-opaque alpha mode
+    // (see SurfacePreferences::with_alpha_modes)
+    pub transparent: bool,
+    pub icon: Option<WindowIcon>,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            inner_size: None,
+            min_inner_size: None,
+            max_inner_size: None,
+            position: None,
+            fullscreen: None,
+            resizable: true,
+            decorations: true,
+            transparent: false,
+            icon: None,
+        }
+    }
+}
+
+impl WindowOptions {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_inner_size<S: Into<Size>>(mut self, size: S) -> Self {
+        self.inner_size = Some(size.into());
+        self
+    }
+
+    pub fn with_min_inner_size<S: Into<Size>>(mut self, size: S) -> Self {
+        self.min_inner_size = Some(size.into());
+        self
+    }
+
+    pub fn with_max_inner_size<S: Into<Size>>(mut self, size: S) -> Self {
+        self.max_inner_size = Some(size.into());
+        self
+    }
+
+    pub fn with_position<P: Into<Position>>(mut self, position: P) -> Self {
+        self.position = Some(position.into());
+        self
+    }
+
+    pub fn with_fullscreen(mut self, fullscreen: Option<FullscreenMode>) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    pub fn with_decorations(mut self, decorations: bool) -> Self {
+        self.decorations = decorations;
+        self
+    }
+
+    pub fn with_transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    pub fn with_icon(mut self, icon: WindowIcon) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    pub fn window_builder<T>(&self, target: &EventLoopWindowTarget<T>) -> Result<WindowBuilder, GraphicsInitError> {
+        let mut builder = WindowBuilder::new()
+            .with_title(self.title.as_str())
+            .with_resizable(self.resizable)
+            .with_decorations(self.decorations)
+            .with_transparent(self.transparent)
+            .with_fullscreen(self.fullscreen.and_then(|mode| fullscreen_for(mode, target.primary_monitor())));
+
+        if let Some(size) = self.inner_size {
+            builder = builder.with_inner_size(size);
+        }
+        if let Some(size) = self.min_inner_size {
+            builder = builder.with_min_inner_size(size);
+        }
+        if let Some(size) = self.max_inner_size {
+            builder = builder.with_max_inner_size(size);
+        }
+        if let Some(position) = self.position {
+            builder = builder.with_position(position);
+        }
+        if let Some(icon) = &self.icon {
+            let icon = Icon::from_rgba(icon.rgba.clone(), icon.width, icon.height)
+                .map_err(GraphicsInitError::InvalidIcon)?;
+            builder = builder.with_window_icon(Some(icon));
+        }
+        Ok(builder)
+    }
+}
+
+fn fullscreen_for(mode: FullscreenMode, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+    match mode {
+        FullscreenMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+        FullscreenMode::Exclusive => monitor
+            .and_then(|m| m.video_modes().max_by_key(video_mode_rank))
+            .map(Fullscreen::Exclusive),
+    }
+}
+
+fn video_mode_rank(mode: &VideoMode) -> (u32, u32, u16) {
+    let size = mode.size();
+    (size.width * size.height, mode.refresh_rate_millihertz(), mode.bit_depth())
+}
+
+// ====== runtime window controls ======
+// these are all no-ops on headless skeletons
+
+impl AppSkeleton {
+    pub fn set_fullscreen(&self, fullscreen: Option<FullscreenMode>) {
+        if let Some(window) = &self.window {
+            window.set_fullscreen(fullscreen.and_then(|mode| fullscreen_for(mode, window.current_monitor())));
+        }
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.window.as_ref().is_some_and(|window| window.fullscreen().is_some())
+    }
+
+    pub fn toggle_fullscreen(&self, mode: FullscreenMode) {
+        if self.is_fullscreen() {
+            self.set_fullscreen(None);
+        } else {
+            self.set_fullscreen(Some(mode));
+        }
+    }
+
+    pub fn set_title(&self, title: &str) {
+        if let Some(window) = &self.window {
+            window.set_title(title);
+        }
+    }
+
+    pub fn set_resizable(&self, resizable: bool) {
+        if let Some(window) = &self.window {
+            window.set_resizable(resizable);
+        }
+    }
+
+    pub fn set_decorations(&self, decorations: bool) {
+        if let Some(window) = &self.window {
+            window.set_decorations(decorations);
+        }
+    }
+
+    pub fn set_inner_size<S: Into<Size>>(&self, size: S) {
+        if let Some(window) = &self.window {
+            window.set_inner_size(size);
+        }
+    }
+
+    pub fn set_window_icon(&self, icon: Option<&WindowIcon>) -> Result<(), winit::window::BadIcon> {
+        if let Some(window) = &self.window {
+            let icon = match icon {
+                Some(icon) => Some(Icon::from_rgba(icon.rgba.clone(), icon.width, icon.height)?),
+                None => None,
+            };
+            window.set_window_icon(icon);
+        }
+        Ok(())
+    }
+}
+
+// ====== secondary windows ======
+
+// fields drop in order and the surface must not outlive its window, keep window last
+pub struct SecondaryWindow {
+    pub label: String,
+    pub target: RenderTarget,
+    pub config: wgpu::SurfaceConfiguration,
+    pub window: Window,
+}
+
+impl AppSkeleton {
+    pub fn open_window<T>(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<T>,
+        label: &str,
+        options: &WindowOptions,
+    ) -> Result<WindowId, GraphicsInitError> {
+        let window = options.window_builder(event_loop)?.build(event_loop)?;
+        let surface = crate::create_surface(&window, &self.instance)?;
+        if !self.adapter.is_surface_supported(&surface) {
+            return Err(GraphicsInitError::IncompatibleSurface);
+        }
+
+        // prefer the main surface format so pipelines can be shared between windows
+        let mut preferences = self.surface_preferences.clone();
+        preferences.formats.insert(0, self.config.format);
+        // minimized or not yet laid out windows can report 0x0, which no surface accepts
+        let size = window.inner_size();
+        let size = winit::dpi::PhysicalSize::new(size.width.max(1), size.height.max(1));
+        let config = crate::create_surface_configuration(&surface, &self.adapter, &size, &preferences)?;
+        surface.configure(&self.device, &config);
+
+        // same depth format and sample count too, for the same reason
+        let mut target = RenderTarget::from_surface(surface);
+        target.set_depth_format(&self.device, &config, self.target.depth_format());
+        if self.target.sample_count > 1 {
+            target.set_sample_count(&self.device, &config, self.target.sample_count);
+        }
+
+        let id = window.id();
+        self.windows.insert(id, SecondaryWindow {
+            label: label.to_string(),
+            target,
+            config,
+            window,
+        });
+        Ok(id)
+    }
+
+    pub fn close_window(&mut self, window: WindowId) -> Option<SecondaryWindow> {
+        self.windows.remove(&window)
+    }
+
+    // 0x0 sizes (minimized windows) are ignored
+    pub fn resize_window(&mut self, window: WindowId, size: winit::dpi::PhysicalSize<u32>) {
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        if let Some(secondary) = self.windows.get_mut(&window) {
+            secondary.config.width = size.width;
+            secondary.config.height = size.height;
+            secondary.target.configure(&self.device, &secondary.config);
+        }
+    }
+}